@@ -0,0 +1,70 @@
+//! Times filtering and rendering a synthetic list of 10,000 tasks.
+//!
+//! Run with `cargo bench --bench list`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDate;
+use plansync::config::{Config, View};
+use plansync::manager::TaskManager;
+use plansync::task::Priority;
+
+const TASKS: usize = 10_000;
+const WORDS: [&str; 8] = ["Email", "report", "Review", "Café", "budget", "call", "Plan", "fix"];
+const TAGS: [&str; 5] = ["work", "home", "comms", "errands", "ops"];
+
+fn synthetic_list() -> TaskManager {
+    let mut manager = TaskManager::new();
+    let start = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+    for i in 0..TASKS {
+        let description = format!("{} {} item {}", WORDS[i % 8], WORDS[(i / 8) % 8], i);
+        let due = (i % 3 != 0).then(|| start + chrono::Duration::days((i % 365) as i64));
+        let tags = vec![TAGS[i % 5].to_string(), TAGS[(i / 5) % 5].to_string()];
+        let id = manager.create_task(description, due, tags).unwrap();
+        if i % 4 == 0 {
+            manager.set_priority(id, Priority::High);
+        }
+    }
+    manager
+}
+
+/// Runs `f` repeatedly for about a second and prints the mean time per run.
+fn bench(name: &str, mut f: impl FnMut()) {
+    f();
+    let started = Instant::now();
+    let mut runs = 0u32;
+    while started.elapsed() < Duration::from_secs(1) {
+        f();
+        runs += 1;
+    }
+    println!("{:<24} {:>10.3} ms", name, started.elapsed().as_secs_f64() * 1e3 / runs as f64);
+}
+
+fn main() {
+    let manager = synthetic_list();
+    let config = Config::default();
+    let today = NaiveDate::from_ymd_opt(2026, 6, 1).unwrap();
+    colored::control::set_override(false);
+
+    let views = [
+        ("view: all", View::default()),
+        ("view: text filter", View { filter: Some("review".into()), ..View::default() }),
+        ("view: tag filter", View { tag: Some("comms".into()), ..View::default() }),
+        ("view: tag as text", View { filter: Some("errands".into()), ..View::default() }),
+        ("view: non-ASCII text", View { filter: Some("café".into()), ..View::default() }),
+    ];
+    for (name, view) in &views {
+        bench(name, || {
+            black_box(manager.view_tasks(view, &config, today));
+        });
+    }
+    bench("search: description", || {
+        black_box(manager.find_matches("budget call"));
+    });
+    for (name, view) in &views[..3] {
+        bench(&name.replace("view", "render"), || {
+            black_box(manager.render_tasks(view, &config, today));
+        });
+    }
+}
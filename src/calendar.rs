@@ -0,0 +1,60 @@
+use chrono::{Datelike, Months, NaiveDate};
+use colored::Colorize;
+use std::collections::HashMap;
+
+use plansync::config::WeekStart;
+
+/// Width of one day in the grid: the day number plus room for a count.
+const CELL: usize = 5;
+
+/// The month containing `month` as a grid of days. Days with tasks due show
+/// how many; today is inverted and past days with tasks still due are red.
+pub fn render_month(
+    month: NaiveDate,
+    counts: &HashMap<NaiveDate, usize>,
+    today: NaiveDate,
+    week_start: WeekStart,
+) -> String {
+    let first = month.with_day(1).expect("every month has a first day");
+    let next = first + Months::new(1);
+    let start = week_start.weekday();
+
+    let mut out = format!("{:^width$}\n", first.format("%B %Y").to_string(), width = CELL * 7);
+    let mut weekday = start;
+    for _ in 0..7 {
+        out.push_str(&format!("{:<width$}", &weekday.to_string()[..2], width = CELL));
+        weekday = weekday.succ();
+    }
+    out.push('\n');
+
+    let mut column = first.weekday().days_since(start) as usize;
+    out.push_str(&" ".repeat(CELL * column));
+    for date in first.iter_days().take_while(|date| *date < next) {
+        let count = counts.get(&date).copied().unwrap_or(0);
+        let day = format!("{:>2}", date.day());
+        let marker = match count {
+            0 => " ".repeat(CELL - 2),
+            n => format!("{:<width$}", format!("•{}", n.min(99)), width = CELL - 2),
+        };
+
+        let day = if date == today {
+            day.bold().reversed()
+        } else if count > 0 && date < today {
+            day.red()
+        } else {
+            day.normal()
+        };
+        let marker = if date < today { marker.red() } else { marker.yellow() };
+        out.push_str(&format!("{}{}", day, marker));
+
+        column += 1;
+        if column == 7 {
+            out.push('\n');
+            column = 0;
+        }
+    }
+    if column != 0 {
+        out.push('\n');
+    }
+    out
+}
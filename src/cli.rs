@@ -0,0 +1,2024 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Months, NaiveDate, Utc, Weekday};
+use colored::{Color, Colorize};
+use dialoguer::{
+    theme::{ColorfulTheme, Theme},
+    Completion,
+    {Confirm, Input, Select},
+};
+use console::{Key, Term};
+use std::{
+    collections::HashMap,
+    io,
+    path::Path,
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::calendar::render_month;
+use crate::git;
+use plansync::config::{Config, LengthPolicy, SortOrder, View};
+use plansync::i18n::tr;
+use plansync::import::{import_rows, parse_csv, CsvColumns};
+use plansync::lint::lint;
+use plansync::manager::{diff, read_text_file, render_task, TaskManager};
+use plansync::task::{
+    is_weekend, next_business_day, parse_due_date, single_line, today, Comment, Priority,
+    Recurrence, Status, Task,
+};
+
+struct TagCompletion {
+    tags: Vec<String>,
+}
+
+impl TagCompletion {
+    fn new(counts: &HashMap<String, usize>) -> Self {
+        let mut tags: Vec<(&String, &usize)> = counts.iter().collect();
+        tags.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        TagCompletion {
+            tags: tags.into_iter().map(|(tag, _)| tag.clone()).collect(),
+        }
+    }
+}
+
+impl Completion for TagCompletion {
+    /// Completes the tag currently being typed (the text after the last comma)
+    /// to the most frequently used known tag with that prefix.
+    fn get(&self, input: &str) -> Option<String> {
+        let (head, current) = match input.rfind(',') {
+            Some(pos) => (&input[..=pos], &input[pos + 1..]),
+            None => ("", input),
+        };
+        let prefix = current.trim().to_lowercase();
+        if prefix.is_empty() {
+            return None;
+        }
+
+        let tag = self
+            .tags
+            .iter()
+            .find(|tag| tag.starts_with(&prefix) && **tag != prefix)?;
+        let separator = if head.is_empty() { "" } else { " " };
+        Some(format!("{}{}{}", head, separator, tag))
+    }
+}
+
+pub fn prompt_tags(
+    prompt: &str,
+    default: Option<String>,
+    known: &HashMap<String, usize>,
+    config: &Config,
+) -> Result<Vec<String>> {
+    let theme = ColorfulTheme::default();
+    let completion = TagCompletion::new(known);
+    'prompt: loop {
+        let mut input = Input::<String>::with_theme(&theme)
+            .with_prompt(prompt)
+            .allow_empty(true)
+            .completion_with(&completion);
+        if let Some(default) = &default {
+            input = input.default(default.clone());
+        }
+
+        let tags: Vec<String> = input
+            .interact()?
+            .split(',')
+            .filter_map(|tag| config.canonical_tag(tag))
+            .collect();
+
+        if config.confirm_new_tags {
+            for tag in tags.iter().filter(|tag| !known.contains_key(*tag)) {
+                let keep = Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!("{} '{}'?", tr("Keep the new tag"), tag))
+                    .default(true)
+                    .interact()?;
+                if !keep {
+                    continue 'prompt;
+                }
+            }
+        }
+
+        return Ok(tags);
+    }
+}
+
+fn validate_description(input: &str) -> Result<(), String> {
+    if input.trim().is_empty() {
+        return Err(tr("Description can't be empty").to_string());
+    }
+    Ok(())
+}
+
+fn prompt_description(prompt: &str, default: Option<String>, config: &Config) -> Result<String> {
+    let limit = config.max_description_length;
+    let reject = config.description_length_policy == LengthPolicy::Reject;
+
+    let theme = ColorfulTheme::default();
+    let mut input = Input::<String>::with_theme(&theme)
+        .with_prompt(prompt)
+        .validate_with(move |input: &String| -> Result<(), String> {
+            validate_description(input)?;
+            let length = single_line(input).chars().count();
+            match limit {
+                Some(max) if reject && length > max => {
+                    Err(format!("{} ({} > {})", tr("Description is too long"), length, max))
+                }
+                _ => Ok(()),
+            }
+        });
+    if let Some(default) = default {
+        input = input.default(default);
+    }
+    let description = single_line(&input.interact()?);
+
+    match limit {
+        Some(max) if description.chars().count() > max => {
+            let message = format!("{} {}", tr("Description truncated to characters:"), max);
+            println!("{}", message.yellow());
+            Ok(description.chars().take(max).collect())
+        }
+        _ => Ok(description),
+    }
+}
+
+/// Prompts for an optional date. An empty answer means no date; offsets like
+/// `+1w` count from `base`.
+fn prompt_due_date(prompt: &str, base: NaiveDate) -> Result<Option<NaiveDate>> {
+    let input: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .validate_with(|input: &String| -> Result<(), &str> {
+            if input.is_empty() {
+                return Ok(());
+            }
+            parse_due_date(input, base)
+                .map(|_| ())
+                .ok_or(tr("Invalid date. Use YYYY-MM-DD or an offset like +1d, -2d, +1w, +1m"))
+        })
+        .interact()?;
+    Ok(parse_due_date(&input, base))
+}
+
+/// Offers common due dates before falling back to typing one in.
+fn prompt_due_preset(config: &Config) -> Result<Option<NaiveDate>> {
+    let today = today();
+    let weekend = match today.weekday() {
+        Weekday::Sat | Weekday::Sun => today,
+        day => today + chrono::Duration::days(5 - day.num_days_from_monday() as i64),
+    };
+    let next_week = config.week_start.first_day(today) + chrono::Duration::days(7);
+
+    let presets = [
+        (tr("Today"), Some(today)),
+        (tr("Tomorrow"), today.succ_opt()),
+        (tr("This weekend"), Some(weekend)),
+        (tr("Next week"), Some(next_week)),
+        (tr("No date"), None),
+    ];
+    let mut items: Vec<String> = presets
+        .iter()
+        .map(|(label, date)| match date {
+            Some(date) => format!("{} ({})", label, date.format("%a %Y-%m-%d")),
+            None => label.to_string(),
+        })
+        .collect();
+    items.push(tr("Custom…").to_string());
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(tr("Due date"))
+        .items(&items)
+        .default(presets.len() - 1)
+        .interact()?;
+    match presets.get(selection) {
+        Some((_, date)) => Ok(*date),
+        None => prompt_due_date(tr("Due date (YYYY-MM-DD or +1d/+1w/+1m) (optional)"), today),
+    }
+}
+
+/// With `warn_weekend_due` set, offers to move a weekend `due` date to the
+/// next business day. Returns the date to use.
+fn check_weekend(due: Option<NaiveDate>, config: &Config) -> Result<Option<NaiveDate>> {
+    let Some(date) = due.filter(|date| config.warn_weekend_due && is_weekend(*date)) else {
+        return Ok(due);
+    };
+    let monday = next_business_day(date);
+    let shift = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "{} {} — {} {}?",
+            date.format("%a %Y-%m-%d"),
+            tr("is on a weekend"),
+            tr("move it to"),
+            monday.format("%a %Y-%m-%d")
+        ))
+        .default(true)
+        .interact()?;
+    Ok(Some(if shift { monday } else { date }))
+}
+
+fn prompt_priority(default: Priority) -> Result<Priority> {
+    let labels: Vec<&str> = Priority::ALL.iter().map(|p| tr(p.label())).collect();
+    let current = Priority::ALL.iter().position(|p| *p == default).unwrap_or(0);
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(tr("Priority"))
+        .items(&labels)
+        .default(current)
+        .interact()?;
+    Ok(Priority::ALL[selection])
+}
+
+/// Asks whether a task due on `due` repeats, showing the next few dates of
+/// the chosen schedule for confirmation.
+fn prompt_recurrence(due: NaiveDate) -> Result<Option<Recurrence>> {
+    let mut labels = vec![tr("Doesn't repeat")];
+    labels.extend(Recurrence::ALL.iter().map(|r| tr(r.label())));
+    loop {
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(tr("Repeat"))
+            .items(&labels)
+            .default(0)
+            .interact()?;
+        let Some(&recurrence) = selection.checked_sub(1).and_then(|i| Recurrence::ALL.get(i)) else {
+            return Ok(None);
+        };
+
+        let dates: Vec<String> = recurrence
+            .upcoming(due, 3)
+            .iter()
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .collect();
+        println!("{} {}", tr("Next occurrences:"), dates.join(", "));
+        let keep = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(tr("Keep this schedule?"))
+            .default(true)
+            .interact()?;
+        if keep {
+            return Ok(Some(recurrence));
+        }
+    }
+}
+
+fn edit_task(manager: &mut TaskManager, id: usize, config: &Config) -> Result<()> {
+    let Some(task) = manager.get_task(id) else {
+        return Ok(());
+    };
+    let current_description = task.description.clone();
+    let current_tags = task.tags.join(", ");
+    let current_priority = task.priority;
+    let current_due = task.due_date;
+    let current_estimate = task.estimate_minutes;
+
+    let description = prompt_description(tr("New description"), Some(current_description), config)?;
+
+    let due_date = prompt_due_date(
+        tr("Due date (YYYY-MM-DD or +1d/+1w/+1m) (leave empty to remove)"),
+        current_due.unwrap_or_else(today),
+    )?;
+    // Only a changed date is checked, so keeping a weekend date asks nothing.
+    let due_date = if due_date == current_due {
+        due_date
+    } else {
+        check_weekend(due_date, config)?
+    };
+
+    let tags = prompt_tags(
+        tr("Tags (comma-separated, Tab completes known tags)"),
+        Some(current_tags),
+        &manager.tag_counts(),
+        config,
+    )?;
+
+    let priority = prompt_priority(current_priority)?;
+    let estimate = prompt_estimate(current_estimate)?;
+
+    manager.set_description(id, description);
+    manager.set_due_date(id, due_date);
+    manager.set_tags(id, tags);
+    manager.set_priority(id, priority);
+    manager.set_estimate(id, estimate);
+    Ok(())
+}
+
+fn prompt_estimate(current: Option<u32>) -> Result<Option<u32>> {
+    let input: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(tr("Estimate in minutes (optional)"))
+        .with_initial_text(current.map(|m| m.to_string()).unwrap_or_default())
+        .allow_empty(true)
+        .validate_with(|input: &String| -> Result<(), &str> {
+            if input.trim().is_empty() || input.trim().parse::<u32>().is_ok() {
+                Ok(())
+            } else {
+                Err(tr("Enter a whole number of minutes"))
+            }
+        })
+        .interact()?;
+    Ok(input.trim().parse().ok())
+}
+
+/// Minutes as hours and minutes, e.g. "1h 30m" or "45m".
+fn format_minutes(minutes: u32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h {}m", h, m),
+    }
+}
+
+pub fn prompt_task_id(manager: &TaskManager, prompt: &str) -> Result<Option<usize>> {
+    let query: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .interact()?;
+    resolve_task(manager, &query)
+}
+
+/// Reads a set of ids such as `3,5,7-9`, sorted and without repeats. Fails,
+/// naming the part at fault, unless the whole input has that form.
+fn parse_id_set(input: &str) -> Result<Vec<usize>> {
+    // Anything wider is a typo, not a range worth expanding.
+    const MAX_SPAN: usize = 10_000;
+
+    let mut ids = Vec::new();
+    for part in input.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let range = match part.split_once('-') {
+            Some((from, to)) => from.trim().parse().ok().zip(to.trim().parse().ok()),
+            None => part.parse().ok().map(|id: usize| (id, id)),
+        };
+        let Some((from, to)) = range else {
+            bail!("{} '{}'", tr("Not an id or range:"), part);
+        };
+        let (from, to): (usize, usize) = (from.min(to), from.max(to));
+        if to - from > MAX_SPAN {
+            bail!("{} '{}'", tr("Range too wide:"), part);
+        }
+        ids.extend(from..=to);
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// Like `prompt_task_id`, but also takes a list of ids and ranges such as
+/// `3,5,7-9`. Ids that match no task are reported and skipped.
+fn prompt_task_ids(manager: &TaskManager, prompt: &str) -> Result<Vec<usize>> {
+    let query: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .interact()?;
+
+    // Anything else, "follow-up" say, is a description.
+    let id_set = query.contains([',', '-'])
+        && query.chars().all(|c| c.is_ascii_digit() || c.is_whitespace() || ",-".contains(c));
+    if !id_set {
+        return Ok(resolve_task(manager, &query)?.into_iter().collect());
+    }
+    let ids = match parse_id_set(&query) {
+        Ok(ids) => ids,
+        Err(e) => {
+            println!("{}", format!("{:#}", e).yellow());
+            return Ok(Vec::new());
+        }
+    };
+    let (known, unknown): (Vec<usize>, Vec<usize>) =
+        ids.into_iter().partition(|id| manager.active_tasks().any(|t| t.id == *id));
+    if !unknown.is_empty() {
+        let unknown: Vec<String> = unknown.iter().map(|id| format!("#{}", id)).collect();
+        println!("{} {}", tr("Skipping unknown ids:").yellow(), unknown.join(" ").yellow());
+    }
+    Ok(known)
+}
+
+/// The task `query` names: an id, or a description that may need picking
+/// from several matches.
+fn resolve_task(manager: &TaskManager, query: &str) -> Result<Option<usize>> {
+    let matches = manager.find_matches(query);
+    match matches.len() {
+        0 => Ok(None),
+        1 => Ok(Some(matches[0])),
+        _ => {
+            let items: Vec<String> = matches
+                .iter()
+                .filter_map(|id| manager.tasks.iter().find(|t| t.id == *id))
+                .map(|t| format!("#{} {}", t.id, t.description))
+                .collect();
+
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Several tasks match, pick one"))
+                .items(&items)
+                .default(0)
+                .interact()?;
+            Ok(Some(matches[selection]))
+        }
+    }
+}
+
+/// Opens the data file in `$EDITOR` and loads it back. Returns `None` when the
+/// edited file does not validate; the file itself is left as the user saved it.
+/// Problems go to stderr; `quiet` drops the success message.
+pub fn edit_data_file(path: &Path, quiet: bool) -> Result<Option<TaskManager>> {
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("EDITOR is empty")?;
+
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("{} '{}'", tr("Could not run editor"), editor))?;
+    if !status.success() {
+        eprintln!("{}", format!("{} {}", tr("Editor exited with"), status).yellow());
+    }
+    let kept = tr("The file was kept as edited but not reloaded.");
+
+    match TaskManager::load(path) {
+        Ok(manager) => {
+            let problems = manager.validate();
+            if problems.is_empty() {
+                if !quiet {
+                    let count = manager.tasks.len();
+                    println!("{} {} ({})", tr("Tasks reloaded:"), count, path.display());
+                }
+                Ok(Some(manager))
+            } else {
+                for problem in &problems {
+                    eprintln!("{}", problem.red());
+                }
+                eprintln!("{}", kept.yellow());
+                Ok(None)
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", format!("{} {}: {:#}", tr("Could not parse"), path.display(), e).red());
+            eprintln!("{}", kept.yellow());
+            Ok(None)
+        }
+    }
+}
+
+/// Set by Ctrl-C, for loops that aren't waiting in a prompt.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Lets Ctrl-C inside a prompt come back as an `Interrupted` error instead of
+/// killing the process, so the menu can cancel just the current action.
+#[cfg(unix)]
+fn catch_interrupts() {
+    extern "C" fn flag(_: libc::c_int) {
+        INTERRUPTED.store(true, Ordering::SeqCst);
+    }
+    let handler: extern "C" fn(libc::c_int) = flag;
+    unsafe {
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+fn catch_interrupts() {}
+
+fn is_interrupted(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| match cause.downcast_ref::<dialoguer::Error>() {
+        Some(dialoguer::Error::IO(e)) => e.kind() == io::ErrorKind::Interrupted,
+        None => cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::Interrupted),
+    })
+}
+
+/// Counts down `minutes`, redrawing the remaining time in place. Ctrl-C stops
+/// it with an `Interrupted` error.
+fn countdown(label: &str, minutes: u64) -> Result<()> {
+    let term = Term::stdout();
+    let end = Instant::now() + Duration::from_secs(minutes * 60);
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    term.hide_cursor()?;
+    loop {
+        if INTERRUPTED.swap(false, Ordering::SeqCst) {
+            term.show_cursor()?;
+            return Err(io::Error::from(io::ErrorKind::Interrupted).into());
+        }
+        let left = end.saturating_duration_since(Instant::now());
+        let seconds = left.as_millis().div_ceil(1000);
+        term.clear_line()?;
+        term.write_str(&format!("{} {:02}:{:02}", label, seconds / 60, seconds % 60))?;
+        if left.is_zero() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(200));
+    }
+    term.write_line("")?;
+    term.show_cursor()?;
+    Ok(())
+}
+
+/// Shows the task `next_task` picks and lets the user complete it or work on
+/// it for a pomodoro, which is logged against the task.
+fn focus(manager: &mut TaskManager, config: &Config) -> Result<Flow> {
+    let Some(task) = manager.next_task() else {
+        println!("{}", tr("Nothing left to do!").green());
+        return Ok(Flow::Skip);
+    };
+    let id = task.id;
+
+    println!("\n{}", tr("Focus:").bold().underline());
+    println!("{}", render_task(task, config, today(), None));
+    if !task.notes.is_empty() {
+        println!("{}", task.notes.dimmed());
+    }
+    if !task.time_entries.is_empty() {
+        println!("{} {} min", tr("Tracked:"), task.tracked_minutes());
+    }
+    println!();
+
+    let work = config.pomodoro_minutes.unwrap_or(25);
+    let rest = config.break_minutes.unwrap_or(5);
+    let actions = [
+        tr("Mark as done").to_string(),
+        format!("{} ({} min)", tr("Start a pomodoro"), work),
+        tr("Back to menu").to_string(),
+    ];
+    let action = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(tr("What now?"))
+        .items(&actions)
+        .default(0)
+        .interact()?;
+    match action {
+        0 => {}
+        1 => {
+            let started_at = Utc::now();
+            countdown(tr("Focus"), work)?;
+            manager.log_time(id, started_at, Utc::now());
+            println!("{}", tr("Pomodoro finished!").green());
+
+            let actions = [
+                tr("Mark as done").to_string(),
+                format!("{} ({} min)", tr("Start a break"), rest),
+                tr("Back to menu").to_string(),
+            ];
+            // The logged session is saved however this ends, even cancelled.
+            let action = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("What now?"))
+                .items(&actions)
+                .default(0)
+                .interact();
+            match action {
+                Ok(0) => {}
+                Ok(1) => {
+                    if countdown(tr("Break"), rest).is_ok() {
+                        println!("{}", tr("Break over.").green());
+                    }
+                    return Ok(Flow::Save);
+                }
+                _ => return Ok(Flow::Save),
+            }
+        }
+        _ => return Ok(Flow::Skip),
+    }
+
+    if let Some(task) = manager.complete_task(id, None) {
+        println!("{} #{}: {}", tr("Completed task"), task.id, task.description);
+    }
+    Ok(Flow::Save)
+}
+
+/// Walks through adding a task, with `tags` offered as the starting tags.
+/// Returns the new task's id, or `None` if nothing was added.
+fn add_task(
+    manager: &mut TaskManager,
+    config: &Config,
+    tags: Option<String>,
+) -> Result<Option<usize>> {
+    let description = prompt_description(tr("Task description"), None, config)?;
+    if let Some(existing) = manager.find_duplicate(&description) {
+        let add_anyway = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "{} '{}' {}",
+                tr("A task"),
+                existing.description,
+                tr("already exists — add anyway?")
+            ))
+            .default(false)
+            .interact()?;
+        if !add_anyway {
+            return Ok(None);
+        }
+    }
+
+    let due_date = check_weekend(prompt_due_preset(config)?, config)?;
+    let recurrence = match due_date {
+        Some(due) => prompt_recurrence(due)?,
+        None => None,
+    };
+
+    let mut tags = prompt_tags(
+        tr("Tags (comma-separated, optional, Tab completes known tags)"),
+        tags,
+        &manager.tag_counts(),
+        config,
+    )?;
+    let auto_tags = config.auto_tags_for(&description, &tags);
+    if !auto_tags.is_empty() {
+        println!("{} {}", tr("Auto-tagged:").dimmed(), auto_tags.join(", ").dimmed());
+        tags.extend(auto_tags);
+    }
+
+    let priority = prompt_priority(config.default_priority)?;
+
+    let due_date = due_date.or_else(|| config.default_due_date(&tags, today()));
+    let id = match manager.create_task(description, due_date, tags) {
+        Ok(id) => id,
+        Err(e) => {
+            println!("{}", format!("{:#}", e).red());
+            return Ok(None);
+        }
+    };
+    manager.set_priority(id, priority);
+    manager.set_recurrence(id, recurrence);
+    Ok(Some(id))
+}
+
+/// Offers to add a task following on from the completed `ids`, starting from
+/// their tags. Cancelling skips the follow-up but keeps the completions.
+fn offer_follow_up(manager: &mut TaskManager, config: &Config, ids: &[usize]) -> Result<()> {
+    match add_follow_up(manager, config, ids) {
+        Err(e) if is_interrupted(&e) => {
+            println!("\n{}", tr("Cancelled.").yellow());
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn add_follow_up(manager: &mut TaskManager, config: &Config, ids: &[usize]) -> Result<()> {
+    let add = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(tr("Add a follow-up task?"))
+        .default(false)
+        .interact()?;
+    if !add {
+        return Ok(());
+    }
+
+    let mut tags: Vec<&str> = Vec::new();
+    for tag in ids.iter().filter_map(|id| manager.get_task(*id)).flat_map(|t| &t.tags) {
+        if !tags.contains(&tag.as_str()) {
+            tags.push(tag);
+        }
+    }
+    let tags = (!tags.is_empty()).then(|| tags.join(", "));
+    add_task(manager, config, tags)?;
+    Ok(())
+}
+
+/// Offers the recently changed tasks, then completing, editing or showing the
+/// one picked.
+fn act_on_recent(manager: &mut TaskManager, config: &Config, recent: &[usize]) -> Result<Flow> {
+    let tasks: Vec<&Task> = recent
+        .iter()
+        .filter_map(|id| manager.get_task(*id))
+        .filter(|t| t.trashed_at.is_none())
+        .collect();
+    if tasks.is_empty() {
+        println!("{}", tr("No tasks changed yet this session.").dimmed());
+        return Ok(Flow::Skip);
+    }
+    let items: Vec<String> = tasks
+        .iter()
+        .map(|t| format!("#{} {} ({})", t.id, t.description, tr(t.status.label())))
+        .collect();
+    let picked = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(tr("Recent tasks"))
+        .items(&items)
+        .default(0)
+        .interact()?;
+    let id = tasks[picked].id;
+
+    let actions = [tr("Complete Task"), tr("Edit Task"), tr("Task Details")];
+    let action = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(tr("What now?"))
+        .items(&actions)
+        .default(0)
+        .interact()?;
+    match action {
+        0 => {
+            if complete_and_report(manager, id, None) {
+                offer_follow_up(manager, config, &[id])?;
+            }
+        }
+        1 => edit_task(manager, id, config)?,
+        _ => {
+            if let Some(task) = manager.get_task(id) {
+                show_details(task, config)?;
+            }
+            return Ok(Flow::Skip);
+        }
+    }
+    Ok(Flow::Save)
+}
+
+/// Asks `prompt` before an action on `count` tasks when that is more than
+/// `bulk_confirm_threshold`; smaller batches go ahead without asking.
+fn confirm_bulk(prompt: &str, count: usize, config: &Config) -> Result<bool> {
+    if count <= config.bulk_threshold() {
+        return Ok(true);
+    }
+    Ok(Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("{} ({})", prompt, count))
+        .default(false)
+        .interact()?)
+}
+
+/// Completes a task and says so, along with the id of its next occurrence if
+/// it repeats. Returns false, leaving it alone, if it was done already.
+fn complete_and_report(manager: &mut TaskManager, id: usize, note: Option<&str>) -> bool {
+    if let Some(task) = manager.get_task(id).filter(|t| t.status == Status::Done) {
+        println!("{}", format!("#{} {}", task.id, tr("is already done.")).yellow());
+        return false;
+    }
+    let next_id = manager.next_id;
+    if let Some(task) = manager.complete_task(id, note) {
+        println!("{} #{}: {}", tr("Completed task"), task.id, task.description);
+    }
+    if let Some(next) = manager.get_task(next_id) {
+        println!("{} #{}", tr("Next occurrence added as"), next.id);
+    }
+    true
+}
+
+fn print_comment(comment: &Comment) {
+    println!(
+        "  {} {}",
+        comment.created_at.format("%Y-%m-%d %H:%M").to_string().dimmed(),
+        comment.text
+    );
+}
+
+/// Prints everything known about a task. Only the latest comment is shown
+/// unless the user asks for the rest.
+fn show_details(task: &Task, config: &Config) -> Result<()> {
+    println!("\n{}", render_task(task, config, today(), None));
+    println!("{} {}", tr("Priority:"), tr(task.priority.label()));
+    if config.hide_tags && !task.tags.is_empty() {
+        println!("{} {}", tr("Tags:"), task.tags.join(", "));
+    }
+    for tag in &task.tags {
+        if let Some(description) = config.file_tag(tag).and_then(|m| m.description.as_ref()) {
+            println!("  #{}: {}", tag, description);
+        }
+    }
+    if let Some(recurrence) = task.recurrence {
+        println!("{} {}", tr("Repeats:"), tr(recurrence.label()));
+    }
+    if let Some(name) = &task.waiting_on {
+        println!("{} {}", tr("Waiting on:"), name);
+    }
+    if let Some(minutes) = task.estimate_minutes {
+        println!("{} {}", tr("Estimate:"), format_minutes(minutes));
+    }
+    if !task.notes.is_empty() {
+        println!("{}\n{}", tr("Notes:"), task.notes);
+    }
+    if !task.time_entries.is_empty() {
+        println!("{} {} min", tr("Tracked:"), task.tracked_minutes());
+    }
+
+    if !task.attachments.is_empty() {
+        println!("{}", tr("Attachments:"));
+        for target in &task.attachments {
+            println!("  {}", target);
+        }
+    }
+
+    if let Some(latest) = task.comments.last() {
+        println!("{} ({}):", tr("Comments"), task.comments.len());
+        print_comment(latest);
+        if task.comments.len() > 1 {
+            let show_all = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Show all comments?"))
+                .default(false)
+                .interact()?;
+            if show_all {
+                task.comments.iter().for_each(print_comment);
+            }
+        }
+    }
+    println!();
+    Ok(())
+}
+
+fn is_url(target: &str) -> bool {
+    target.contains("://")
+}
+
+/// Opens a file or URL with the desktop's default application.
+fn open_attachment(target: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        // Not `cmd /C start`: cmd would run anything after an `&` in the target,
+        // and attachments can come from other people's files.
+        let mut command = Command::new("rundll32");
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    command
+        .arg(target)
+        .spawn()
+        .with_context(|| format!("Could not open '{}'", target))?;
+    Ok(())
+}
+
+fn manage_attachments(manager: &mut TaskManager, id: usize) -> Result<Flow> {
+    let attachments = manager.get_task(id).map(|t| t.attachments.clone()).unwrap_or_default();
+    let mut actions = vec![tr("Add attachment")];
+    if !attachments.is_empty() {
+        actions.extend([tr("Open attachment"), tr("Remove attachment")]);
+    }
+    actions.push(tr("Back to menu"));
+
+    let action = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(tr("Attachments"))
+        .items(&actions)
+        .default(0)
+        .interact()?;
+    let action = actions[action];
+
+    if action == tr("Add attachment") {
+        let target: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt(tr("File path or URL"))
+            .interact()?;
+        let target = target.trim().to_string();
+        if target.is_empty() {
+            return Ok(Flow::Skip);
+        }
+        if !is_url(&target) && !Path::new(&target).exists() {
+            let warning = format!("'{}' {}", target, tr("does not exist; attaching it anyway"));
+            println!("{}", warning.yellow());
+        }
+        manager.add_attachment(id, target);
+        println!("{} #{}", tr("Updated task"), id);
+        return Ok(Flow::Save);
+    }
+    if action == tr("Back to menu") {
+        return Ok(Flow::Skip);
+    }
+
+    let index = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(action)
+        .items(&attachments)
+        .default(0)
+        .interact()?;
+    if action == tr("Open attachment") {
+        if let Err(e) = open_attachment(&attachments[index]) {
+            println!("{}", format!("{:#}", e).red());
+        }
+        Ok(Flow::Skip)
+    } else {
+        manager.remove_attachment(id, index);
+        println!("{} #{}", tr("Updated task"), id);
+        Ok(Flow::Save)
+    }
+}
+
+/// Saves the list to `data_file`. When that fails the session carries on:
+/// the user can retry, write the list somewhere else, or keep the changes in
+/// memory for the next save. Returns whether `data_file` was written.
+fn save_or_recover(manager: &TaskManager, data_file: &Path) -> Result<bool> {
+    loop {
+        let Err(e) = manager.save(data_file) else {
+            return Ok(true);
+        };
+        eprintln!("\n{}", format!("{} {:#}", tr("Could not save your tasks:"), e).red().bold());
+
+        // Leaving either prompt with Esc or Ctrl-C counts as continuing without
+        // saving; quitting here would lose the changes.
+        let choices = [tr("Retry"), tr("Save to another file"), tr("Continue without saving")];
+        let choice = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(tr("Your changes are not saved yet"))
+            .items(&choices)
+            .default(0)
+            .interact_opt();
+        match choice {
+            Ok(Some(0)) => continue,
+            Ok(Some(1)) => {
+                let path = Input::<String>::with_theme(&ColorfulTheme::default())
+                    .with_prompt(tr("Output file"))
+                    .interact();
+                if let Ok(path) = path {
+                    match manager.save(Path::new(&path)) {
+                        Ok(()) => {
+                            println!("{} {}", tr("Saved a copy to"), path);
+                            return Ok(false);
+                        }
+                        Err(e) => {
+                            eprintln!("{}", format!("{:#}", e).red());
+                            continue;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        println!("{}", tr("Keeping the changes in memory for the next save.").yellow());
+        return Ok(false);
+    }
+}
+
+/// Prints what changed between `before` and `after`, one task per line.
+fn print_changes(before: &TaskManager, after: &TaskManager) {
+    let changes = diff(before, after);
+    println!("\n{}", tr("Changes this session:").bold().underline());
+    if changes.is_empty() {
+        println!("{}", tr("Nothing changed.").dimmed());
+    }
+    let groups = [
+        ("+", &changes.added, Color::Green),
+        ("✓", &changes.completed, Color::Green),
+        ("~", &changes.edited, Color::Yellow),
+        ("-", &changes.deleted, Color::Red),
+    ];
+    for (mark, tasks, color) in groups {
+        for task in tasks {
+            println!("{} #{} {}", mark.color(color), task.id, task.description);
+        }
+    }
+    println!();
+}
+
+/// `save_or_recover`, then, with `git_history` on, a commit of the saved
+/// file. A failed commit only warns.
+fn save_and_record(
+    manager: &TaskManager,
+    config: &Config,
+    data_file: &Path,
+    action: &str,
+) -> Result<bool> {
+    let saved = save_or_recover(manager, data_file)?;
+    if saved {
+        git::record(data_file, config, action);
+    }
+    Ok(saved)
+}
+
+/// When the file holds more tasks than `max_tasks`, offers to archive the
+/// finished ones with a single keypress. Returns whether it archived. Not
+/// offered with `manual_save`, as archiving writes both files at once.
+fn suggest_archive(manager: &mut TaskManager, config: &Config, data_file: &Path) -> Result<bool> {
+    let limit = config.task_limit();
+    let finished = manager.active_tasks().filter(|t| !t.status.is_open()).count();
+    if config.read_only || config.manual_save || manager.tasks.len() <= limit || finished == 0 {
+        return Ok(false);
+    }
+
+    println!(
+        "{}",
+        format!(
+            "{} {} ({} {}). {} {} {}",
+            tr("Your list holds"),
+            manager.tasks.len(),
+            tr("suggested limit"),
+            limit,
+            tr("Press A to archive"),
+            finished,
+            tr("finished task(s), or any other key to carry on.")
+        )
+        .yellow()
+    );
+    // Ctrl-C or a failed read carries on, like any other key.
+    if !matches!(Term::stderr().read_key(), Ok(Key::Char('a' | 'A'))) {
+        return Ok(false);
+    }
+
+    let archive = data_file.with_file_name("archive.json");
+    let count = manager.archive_finished(&archive)?;
+    println!("{} {} → {}", tr("Archived"), count, archive.display());
+    Ok(true)
+}
+
+/// What the menu loop should do once an action has finished.
+enum Flow {
+    Save,
+    Skip,
+    Exit,
+}
+
+fn perform(
+    selection: usize,
+    manager: &mut TaskManager,
+    config: &mut Config,
+    config_file: &Path,
+    data_file: &Path,
+) -> Result<Flow> {
+    match selection {
+        0 => {
+            if add_task(manager, config, None)?.is_none() {
+                return Ok(Flow::Skip);
+            }
+        }
+        1 => manager.list_tasks(&config.view, config),
+        2 => {
+            let prompt = tr("Task IDs (e.g. 3,5,7-9) or description to complete");
+            let ids = prompt_task_ids(manager, prompt)?;
+            if ids.is_empty() {
+                println!("{}", tr("Task not found!").red());
+                return Ok(Flow::Skip);
+            }
+            if !confirm_bulk(tr("Complete these tasks?"), ids.len(), config)? {
+                return Ok(Flow::Skip);
+            }
+
+            let note: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Completion note (optional)"))
+                .allow_empty(true)
+                .interact()?;
+            let note = note.trim();
+            let note = (!note.is_empty()).then_some(note);
+
+            let done: Vec<usize> =
+                ids.into_iter().filter(|&id| complete_and_report(manager, id, note)).collect();
+            if !done.is_empty() {
+                offer_follow_up(manager, config, &done)?;
+            }
+        }
+        3 => {
+            let prompt = tr("Task IDs (e.g. 3,5,7-9) or description to delete");
+            let ids = prompt_task_ids(manager, prompt)?;
+            if ids.is_empty() {
+                println!("{}", tr("Task not found!").red());
+                return Ok(Flow::Skip);
+            }
+            if !confirm_bulk(tr("Delete these tasks?"), ids.len(), config)? {
+                return Ok(Flow::Skip);
+            }
+
+            for id in ids {
+                if let Some(task) = manager.delete_task(id) {
+                    println!("{} #{}: {}", tr("Moved to the trash"), task.id, task.description);
+                }
+            }
+        }
+        4 => {
+            let task_id = prompt_task_id(manager, tr("Task ID or description to edit"))?;
+
+            if let Some(id) = task_id {
+                edit_task(manager, id, config)?;
+                println!("{} #{}", tr("Updated task"), id);
+            } else {
+                println!("{}", tr("Task not found!").red());
+            }
+        }
+        5 => {
+            let filter: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Search (tag or text; due:overdue, due before 2025-02-01…)"))
+                .interact()?;
+
+            let view = View {
+                filter: Some(filter),
+                ..config.view.clone()
+            };
+            manager.list_tasks(&view, config);
+        }
+        6 => {
+            let labels: Vec<&str> = SortOrder::ALL.iter().map(|s| tr(s.label())).collect();
+            let current = SortOrder::ALL
+                .iter()
+                .position(|s| *s == config.view.sort)
+                .unwrap_or(0);
+            let sort = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Sort by"))
+                .items(&labels)
+                .default(current)
+                .interact()?;
+
+            let filter: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Filter (tag or text, optional)"))
+                .default(config.view.filter.clone().unwrap_or_default())
+                .allow_empty(true)
+                .interact()?;
+
+            let mut levels = vec![tr("Any")];
+            levels.extend(Priority::ALL.iter().map(|p| tr(p.label())));
+            let current = config.view.min_priority.map_or(0, |min| {
+                1 + Priority::ALL.iter().position(|p| *p == min).unwrap_or(0)
+            });
+            let level = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Lowest priority to show"))
+                .items(&levels)
+                .default(current)
+                .interact()?;
+
+            let hide_completed = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Hide completed tasks?"))
+                .default(config.view.hide_completed)
+                .interact()?;
+
+            config.view = View {
+                sort: SortOrder::ALL[sort],
+                filter: (!filter.is_empty()).then_some(filter),
+                tag: None,
+                min_priority: level.checked_sub(1).map(|i| Priority::ALL[i]),
+                hide_completed,
+                someday: false,
+                waiting: false,
+            };
+            config.save(config_file)?;
+            manager.list_tasks(&config.view, config);
+        }
+        7 => {
+            config.view = View::default();
+            config.save(config_file)?;
+            println!("{}", tr("View reset to defaults"));
+        }
+        8 => {
+            let path: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Output file"))
+                .interact()?;
+
+            let filter: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Search (tag or text, optional)"))
+                .default(config.view.filter.clone().unwrap_or_default())
+                .allow_empty(true)
+                .interact()?;
+
+            let view = View {
+                filter: (!filter.is_empty()).then_some(filter),
+                ..config.view.clone()
+            };
+            match manager.write_tasks(Path::new(&path), &view, config) {
+                Ok(()) => println!("{} {}", tr("Saved listing to"), path),
+                Err(e) => println!("{}", format!("{:#}", e).red()),
+            }
+        }
+        // `run` has saved any pending changes first.
+        9 => {
+            match edit_data_file(data_file, false)? {
+                Some(edited) => {
+                    *manager = edited;
+                    config.file_tags = manager.tag_meta.clone();
+                }
+                // Don't overwrite the user's edits with the in-memory list.
+                None => return Ok(Flow::Skip),
+            }
+        }
+        10 => {
+            let path: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("File to merge"))
+                .interact()?;
+
+            let path = Path::new(&path);
+            if !path.exists() {
+                println!("{}", tr("File not found!").red());
+                return Ok(Flow::Skip);
+            }
+            let other = match TaskManager::load(path) {
+                Ok(other) => other,
+                Err(e) => {
+                    let message = format!("{} {}: {:#}", tr("Could not read"), path.display(), e);
+                    println!("{}", message.red());
+                    return Ok(Flow::Skip);
+                }
+            };
+
+            let skip_duplicates = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Skip exact duplicates?"))
+                .default(true)
+                .interact()?;
+
+            let report = match manager.merge(other, skip_duplicates) {
+                Ok(report) => report,
+                Err(e) => {
+                    println!("{}", format!("{:#}", e).red());
+                    return Ok(Flow::Skip);
+                }
+            };
+            config.file_tags = manager.tag_meta.clone();
+            println!(
+                "{} {}, {} {}, {} {}",
+                tr("Added:"),
+                report.added,
+                tr("updated:"),
+                report.updated,
+                tr("duplicates skipped:"),
+                report.skipped
+            );
+        }
+        11 => {
+            let trashed = manager.trashed_tasks();
+            if trashed.is_empty() {
+                println!("{}", tr("The trash is empty."));
+                return Ok(Flow::Skip);
+            }
+            let ids: Vec<usize> = trashed.iter().map(|t| t.id).collect();
+            let items: Vec<String> = trashed
+                .iter()
+                .map(|t| format!("#{} {}", t.id, t.description))
+                .collect();
+
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Task to restore"))
+                .items(&items)
+                .default(0)
+                .interact()?;
+
+            if let Some(task) = manager.restore_task(ids[selection]) {
+                println!("{} #{}: {}", tr("Restored task"), task.id, task.description);
+            }
+        }
+        12 => {
+            let count = manager.trashed_tasks().len();
+            if count == 0 {
+                println!("{}", tr("The trash is empty."));
+                return Ok(Flow::Skip);
+            }
+            let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("{} ({})", tr("Permanently delete the trashed tasks?"), count))
+                .default(false)
+                .interact()?;
+            if !confirmed {
+                return Ok(Flow::Skip);
+            }
+            println!("{} {}", tr("Permanently deleted tasks:"), manager.empty_trash());
+        }
+        13 => {
+            let today = today();
+            let stats = manager.stats(today);
+            let (current, longest) = manager.streaks(today);
+
+            let done_this_week = manager.completed_since(config.week_start.first_day(today));
+            let days = tr("day(s)");
+            let overdue_age = |days: Option<f64>| match days {
+                Some(days) => format!("{:.0}d", days).red(),
+                None => "-".dimmed(),
+            };
+            let rows = [
+                (tr("Total"), stats.total.to_string().normal()),
+                (tr("Completed"), stats.completed.to_string().green()),
+                (tr("Open"), stats.open.to_string().normal()),
+                (tr("Cancelled"), stats.cancelled.to_string().dimmed()),
+                (tr("Completion rate"), format!("{:.0}%", stats.completion_rate()).normal()),
+                (tr("Overdue"), stats.overdue.to_string().red()),
+                (tr("Avg overdue"), overdue_age(stats.avg_overdue())),
+                (tr("Oldest overdue"), overdue_age(stats.oldest_overdue.map(|d| d as f64))),
+                (tr("Due today"), stats.due_today.to_string().yellow()),
+                (tr("Done this week"), done_this_week.to_string().normal()),
+                (tr("Current streak"), format!("{} {}", current, days).normal()),
+                (tr("Longest streak"), format!("{} {}", longest, days).normal()),
+            ];
+            let width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+
+            println!("\n{}", tr("Stats:").bold().underline());
+            for (label, value) in rows {
+                let label = format!("{}:", label);
+                println!("{:<width$} {}", label, value, width = width + 1);
+            }
+            println!();
+            return Ok(Flow::Skip);
+        }
+        14 => {
+            let days = config.report_days.unwrap_or(14);
+            let counts = manager.completions_per_day(today(), days);
+            let widest = counts.values().copied().max().unwrap_or(0);
+
+            let title = format!("{} {} {}:", tr("Completed per day, last"), days, tr("day(s)"));
+            println!("\n{}", title.bold().underline());
+            for (day, count) in &counts {
+                let bar = "█".repeat(*count);
+                println!(
+                    "{} {:>width$} {}",
+                    day.format("%a %Y-%m-%d"),
+                    count,
+                    bar.green(),
+                    width = widest.to_string().len()
+                );
+            }
+            println!();
+            return Ok(Flow::Skip);
+        }
+        15 => return focus(manager, config),
+        16 => {
+            let task_id = prompt_task_id(manager, tr("Task ID or description to show"))?;
+
+            match task_id.and_then(|id| manager.get_task(id)) {
+                Some(task) => show_details(task, config)?,
+                None => println!("{}", tr("Task not found!").red()),
+            }
+            return Ok(Flow::Skip);
+        }
+        17 => {
+            let Some(id) = prompt_task_id(manager, tr("Task ID or description to comment on"))?
+            else {
+                println!("{}", tr("Task not found!").red());
+                return Ok(Flow::Skip);
+            };
+            let text: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Comment"))
+                .interact()?;
+            let text = text.trim();
+            if text.is_empty() {
+                return Ok(Flow::Skip);
+            }
+            manager.add_comment(id, text.to_string());
+            println!("{} #{}", tr("Added a comment to task"), id);
+        }
+        18 => {
+            let task_id = prompt_task_id(manager, tr("Task ID or description"))?;
+
+            match task_id {
+                Some(id) => return manage_attachments(manager, id),
+                None => println!("{}", tr("Task not found!").red()),
+            }
+            return Ok(Flow::Skip);
+        }
+        19 => {
+            let view = View {
+                someday: true,
+                ..config.view.clone()
+            };
+            manager.list_tasks(&view, config);
+        }
+        20 => {
+            let task_id = prompt_task_id(manager, tr("Task ID or description to move"))?;
+
+            match task_id.and_then(|id| manager.toggle_someday(id)) {
+                Some(task) if task.someday => {
+                    println!("{} #{}: {}", tr("Moved to Someday"), task.id, task.description)
+                }
+                Some(task) => {
+                    println!("{} #{}: {}", tr("Moved back to active"), task.id, task.description)
+                }
+                None => println!("{}", tr("Task not found!").red()),
+            }
+        }
+        21 => {
+            let Some(id) = prompt_task_id(manager, tr("Task ID or description"))? else {
+                println!("{}", tr("Task not found!").red());
+                return Ok(Flow::Skip);
+            };
+            let current = manager.get_task(id).map(|t| t.status).unwrap_or_default();
+            let labels: Vec<&str> = Status::ALL.iter().map(|s| tr(s.label())).collect();
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Status"))
+                .items(&labels)
+                .default(Status::ALL.iter().position(|s| *s == current).unwrap_or(0))
+                .interact()?;
+
+            if let Some(task) = manager.set_status(id, Status::ALL[selection]) {
+                println!("{} #{}: {}", tr("Updated task"), task.id, tr(task.status.label()));
+            }
+        }
+        22 => {
+            let task_id = prompt_task_id(manager, tr("Task ID or description to cancel"))?;
+
+            if let Some(task) = task_id.and_then(|id| manager.set_status(id, Status::Cancelled)) {
+                println!("{} #{}: {}", tr("Cancelled task"), task.id, task.description);
+            } else {
+                println!("{}", tr("Task not found!").red());
+            }
+        }
+        23 => {
+            let history = manager.history();
+            println!("\n{}", tr("History:").bold().underline());
+            if history.is_empty() {
+                println!("{}", tr("Nothing finished yet.").dimmed());
+            }
+            for task in history {
+                println!("{}", render_task(task, config, today(), None));
+            }
+            println!();
+            return Ok(Flow::Skip);
+        }
+        24 => {
+            let today = today();
+            let overdue = manager.overdue_tasks(today);
+            println!("\n{}", tr("Overdue:").bold().underline());
+            if overdue.is_empty() {
+                println!("{}", tr("Nothing is overdue.").dimmed());
+            }
+            for task in overdue {
+                let late = task.due_date.map_or(0, |due| (today - due).num_days());
+                let late = format!("{} {}", late, tr("day(s) late"));
+                println!("{} {}", render_task(task, config, today, None), late.dimmed());
+            }
+            println!();
+            return Ok(Flow::Skip);
+        }
+        25 => {
+            let tags = manager.tag_counts();
+            let completion = TagCompletion::new(&tags);
+            let tag: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Tag to complete"))
+                .completion_with(&completion)
+                .interact()?;
+
+            let ids = manager.open_with_tag(&tag, config);
+            if ids.is_empty() {
+                println!("{}", format!("{} '{}'", tr("No open tasks are tagged"), tag).yellow());
+                return Ok(Flow::Skip);
+            }
+            for task in ids.iter().filter_map(|id| manager.get_task(*id)) {
+                println!("{}", render_task(task, config, today(), None));
+            }
+            if !confirm_bulk(tr("Complete these tasks?"), ids.len(), config)? {
+                return Ok(Flow::Skip);
+            }
+            let done = manager.complete_by_tag(&tag, config);
+            println!("{} {}", tr("Completed tasks:"), done.len());
+        }
+        26 => {
+            let today = today();
+            let counts = manager.due_counts();
+            let term = Term::stderr();
+            let mut month = today;
+            let mut drawn = 0;
+            loop {
+                term.clear_last_lines(drawn)?;
+                let grid = render_month(month, &counts, today, config.week_start);
+                let hint = tr("← previous month, → next month, any other key to go back");
+                term.write_str(&grid)?;
+                term.write_line(&hint.dimmed().to_string())?;
+                drawn = grid.lines().count() + 1;
+
+                match term.read_key()? {
+                    Key::ArrowLeft => month = month - Months::new(1),
+                    Key::ArrowRight => month = month + Months::new(1),
+                    _ => break,
+                }
+            }
+            return Ok(Flow::Skip);
+        }
+        27 => unreachable!("handled in run"),
+        28 => {
+            let Some(id) = prompt_task_id(manager, tr("Task ID or description"))? else {
+                println!("{}", tr("Task not found!").red());
+                return Ok(Flow::Skip);
+            };
+            let current = manager
+                .get_task(id)
+                .and_then(|t| t.color.as_deref())
+                .and_then(|c| COLOR_NAMES.iter().position(|name| c.eq_ignore_ascii_case(name)))
+                .map_or(0, |i| i + 1);
+            let mut items = vec![tr("Default").to_string()];
+            items.extend(COLOR_NAMES.iter().map(|name| name.color(*name).to_string()));
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Color"))
+                .items(&items)
+                .default(current)
+                .interact()?;
+
+            let color = selection.checked_sub(1).map(|i| COLOR_NAMES[i].to_string());
+            if let Some(task) = manager.set_color(id, color) {
+                println!("{} #{}: {}", tr("Updated task"), task.id, task.description);
+            }
+        }
+        29 => {
+            let Some(last) = manager.last_added() else {
+                println!("{}", tr("No tasks yet — add one!").dimmed());
+                return Ok(Flow::Skip);
+            };
+            if !last.status.is_open() {
+                let status = tr(last.status.label());
+                println!("{} #{}: {} ({})", tr("Last task"), last.id, last.description, status);
+                return Ok(Flow::Skip);
+            }
+            let id = last.id;
+            complete_and_report(manager, id, None);
+            offer_follow_up(manager, config, &[id])?;
+        }
+        30 => {
+            let Some(id) = prompt_task_id(manager, tr("Task ID or description to move"))? else {
+                println!("{}", tr("Task not found!").red());
+                return Ok(Flow::Skip);
+            };
+            let position: usize = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("New position (1 is the top)"))
+                .interact()?;
+            if let Some(position) = manager.move_to_position(id, position) {
+                println!("{} #{} {} {}", tr("Moved task"), id, tr("to position"), position);
+            }
+            if config.view.sort != SortOrder::Manual {
+                let hint = tr("Pick the Manual sort order in Change View to list tasks this way.");
+                println!("{}", hint.dimmed());
+            }
+        }
+        31 => {
+            let path: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("CSV file to import"))
+                .interact()?;
+            let rows = match read_text_file(Path::new(path.trim())) {
+                Ok(contents) => parse_csv(&contents),
+                Err(e) => {
+                    println!("{}", format!("{:#}", e).red());
+                    return Ok(Flow::Skip);
+                }
+            };
+            let Some((header, rows)) = rows.split_first() else {
+                println!("{}", tr("The file has no header row.").red());
+                return Ok(Flow::Skip);
+            };
+
+            let guesses = CsvColumns::guess(header, &config.csv_import_columns);
+            let mut picked = [None; 4];
+            for (i, field) in CsvColumns::FIELDS.iter().enumerate() {
+                // The description is required; every other field can be left out.
+                let optional = i > 0;
+                let mut items: Vec<&str> = Vec::new();
+                if optional {
+                    items.push(tr("(not imported)"));
+                }
+                items.extend(header.iter().map(String::as_str));
+                let offset = usize::from(optional);
+                let selection = Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!("{} {}", tr("Column for"), field))
+                    .items(&items)
+                    .default(guesses[i].map_or(0, |column| column + offset))
+                    .interact()?;
+                picked[i] = selection.checked_sub(offset);
+            }
+            let columns = CsvColumns {
+                description: picked[0].unwrap_or(0),
+                due_date: picked[1],
+                tags: picked[2],
+                completed: picked[3],
+            };
+
+            let report = import_rows(manager, rows, &columns, config)?;
+            for error in &report.errors {
+                println!("{}", error.yellow());
+            }
+            println!(
+                "{} {}, {} {}, {} {}",
+                tr("Imported"),
+                report.added,
+                tr("skipped without a description:"),
+                report.skipped,
+                tr("with errors:"),
+                report.errors.len()
+            );
+        }
+        32 => {
+            let tasks = manager.today_plan(today());
+            println!("\n{}", tr("Today Plan:").bold().underline());
+            if tasks.is_empty() {
+                println!("{}\n", tr("Nothing is due today.").dimmed());
+                return Ok(Flow::Skip);
+            }
+            let mut total = 0;
+            let mut unestimated = 0;
+            for task in tasks {
+                let line = render_task(task, config, today(), None);
+                match task.estimate_minutes {
+                    Some(minutes) => {
+                        total += minutes;
+                        let (minutes, total) = (format_minutes(minutes), format_minutes(total));
+                        println!("{:>7} {:>7}  {}", minutes, total, line);
+                    }
+                    None => {
+                        unestimated += 1;
+                        println!("{:>7} {:>7}  {}", "-".dimmed(), "", line);
+                    }
+                }
+            }
+
+            let capacity = config.daily_capacity();
+            println!(
+                "\n{} {} / {}",
+                tr("Estimated:"),
+                format_minutes(total),
+                format_minutes(capacity)
+            );
+            if unestimated > 0 {
+                let note = format!("{} {}", unestimated, tr("task(s) without an estimate"));
+                println!("{}", note.dimmed());
+            }
+            if total > capacity {
+                let warning = tr("That's more than fits in a day — consider moving something.");
+                println!("{}", warning.red());
+            }
+            println!();
+            return Ok(Flow::Skip);
+        }
+        33 => {
+            let Some(id) = prompt_task_id(manager, tr("Task ID or description"))? else {
+                println!("{}", tr("Task not found!").red());
+                return Ok(Flow::Skip);
+            };
+            let current = manager.get_task(id).and_then(|t| t.waiting_on.clone());
+            let name: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Waiting on whom? (leave empty to clear)"))
+                .with_initial_text(current.unwrap_or_default())
+                .allow_empty(true)
+                .interact()?;
+            if let Some(task) = manager.set_waiting_on(id, &name) {
+                println!("{} #{}: {}", tr("Updated task"), task.id, task.description);
+            }
+        }
+        34 => {
+            let view = View {
+                waiting: true,
+                ..config.view.clone()
+            };
+            manager.list_tasks(&view, config);
+            return Ok(Flow::Skip);
+        }
+        35 => {
+            let findings = lint(manager, config, today());
+            println!("\n{}", tr("Lint:").bold().underline());
+            if findings.is_empty() {
+                println!("{}", tr("Nothing to flag.").dimmed());
+            }
+            for finding in findings {
+                let id = format!("#{}", finding.id).yellow();
+                println!("{} {} — {}", id, finding.problem, finding.suggestion.dimmed());
+            }
+            println!();
+            return Ok(Flow::Skip);
+        }
+        36..=40 => unreachable!("handled in run"),
+        41 => return Ok(Flow::Exit),
+        _ => unreachable!(),
+    }
+    Ok(Flow::Save)
+}
+
+/// The colors a task can be given, as `colored` names them.
+const COLOR_NAMES: [&str; 14] = [
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright red",
+    "bright green",
+    "bright yellow",
+    "bright blue",
+    "bright magenta",
+    "bright cyan",
+    "bright white",
+];
+
+/// The main menu in `perform` order: each entry's shortcut key, its label,
+/// and whether it is offered in read-only mode (it never touches the tasks).
+const MENU: [(char, &str, bool); 42] = [
+    ('a', "Add Task", false),
+    ('l', "List Tasks", true),
+    ('d', "Complete Task", false),
+    ('x', "Delete Task", false),
+    ('e', "Edit Task", false),
+    ('s', "Search Tasks", true),
+    ('v', "Change View", true),
+    ('r', "Reset View", true),
+    ('w', "Save Listing to File", true),
+    ('o', "Edit Data File", false),
+    ('m', "Merge File", false),
+    ('u', "Restore from Trash", false),
+    ('z', "Empty Trash", false),
+    ('#', "Stats", true),
+    ('g', "Completion Report", true),
+    ('f', "Focus", false),
+    ('i', "Task Details", true),
+    ('c', "Add Comment", false),
+    ('t', "Attachments", false),
+    ('y', "Someday", true),
+    ('b', "Move to/from Someday", false),
+    ('k', "Set Status", false),
+    ('n', "Cancel Task", false),
+    ('h', "History", true),
+    ('p', "Overdue", true),
+    ('j', "Complete by Tag", false),
+    ('C', "Calendar", true),
+    ('D', "Session Changes", true),
+    ('P', "Set Color", false),
+    ('L', "Complete Last", false),
+    ('M', "Move to Position", false),
+    ('I', "Import CSV", false),
+    ('T', "Today Plan", true),
+    ('W', "Waiting On", false),
+    ('O', "Waiting", true),
+    ('!', "Lint", true),
+    ('R', "Recent", false),
+    ('S', "Save", false),
+    ('Z', "Reload from Disk", true),
+    ('U', "Undo", false),
+    ('Y', "Redo", false),
+    ('q', "Exit", true),
+];
+
+/// Handled by the loop in `run`, which holds the session's starting point.
+const SESSION_CHANGES: usize = 27;
+/// Also handled in `run`, which keeps the recently changed tasks.
+const RECENT: usize = 36;
+/// Save and Reload from Disk, handled in `run` as they deal with its
+/// unsaved changes.
+const SAVE: usize = 37;
+const RELOAD: usize = 38;
+/// Edit Data File, which `run` makes sure has nothing unsaved to lose.
+const EDIT_DATA_FILE: usize = 9;
+/// Undo and Redo, handled in `run`, which keeps the snapshots.
+const UNDO: usize = 39;
+const REDO: usize = 40;
+/// How many recently changed tasks Recent offers.
+const RECENT_LIMIT: usize = 5;
+/// How many steps Undo can go back, and so Redo forward.
+const UNDO_LIMIT: usize = 20;
+const EXIT: usize = MENU.len() - 1;
+
+/// Pushes `state` onto an undo or redo stack, dropping the oldest entry once
+/// there are more than `UNDO_LIMIT`.
+fn push_bounded(stack: &mut Vec<TaskManager>, state: TaskManager) {
+    stack.push(state);
+    if stack.len() > UNDO_LIMIT {
+        stack.remove(0);
+    }
+}
+
+/// Whether both lists would be saved the same, trash and all.
+fn same_state(a: &TaskManager, b: &TaskManager) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
+/// A `Select` lookalike that also takes an entry's shortcut key, dispatching
+/// at once without moving the cursor there. Arrow keys and Enter still work.
+/// Esc or Ctrl-C gives `None`.
+fn shortcut_select(
+    prompt: &str,
+    items: &[(char, &str)],
+    default: usize,
+) -> Result<Option<usize>> {
+    let theme = ColorfulTheme::default();
+    let term = Term::stderr();
+    let labels: Vec<String> = items
+        .iter()
+        .map(|(key, label)| format!("[{}] {}", key, label))
+        .collect();
+    let rows = (term.size().0 as usize).saturating_sub(2).clamp(1, labels.len());
+
+    let mut active = default;
+    let mut offset = 0;
+    let mut drawn = 0;
+    term.hide_cursor()?;
+    loop {
+        if active < offset {
+            offset = active;
+        } else if active >= offset + rows {
+            offset = active + 1 - rows;
+        }
+
+        term.clear_last_lines(drawn)?;
+        let mut out = String::new();
+        theme.format_select_prompt(&mut out, prompt)?;
+        for (index, label) in labels.iter().enumerate().skip(offset).take(rows) {
+            out.push('\n');
+            theme.format_select_prompt_item(&mut out, label, index == active)?;
+        }
+        term.write_line(&out)?;
+        drawn = rows + 1;
+
+        let key = match term.read_key() {
+            Ok(key) => key,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => Key::Escape,
+            Err(e) => {
+                let _ = term.show_cursor();
+                return Err(e.into());
+            }
+        };
+        match key {
+            Key::ArrowUp => active = (active + labels.len() - 1) % labels.len(),
+            Key::ArrowDown | Key::Tab => active = (active + 1) % labels.len(),
+            Key::Home => active = 0,
+            Key::End => active = labels.len() - 1,
+            Key::Enter => break,
+            Key::Escape => {
+                term.clear_last_lines(drawn)?;
+                term.show_cursor()?;
+                return Ok(None);
+            }
+            Key::Char(c) => {
+                if let Some(index) = items.iter().position(|(key, _)| *key == c) {
+                    active = index;
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    term.clear_last_lines(drawn)?;
+    term.show_cursor()?;
+    let mut out = String::new();
+    theme.format_select_prompt_selection(&mut out, prompt, items[active].1)?;
+    term.write_line(&out)?;
+    Ok(Some(active))
+}
+
+/// The main menu loop. `on_disk` is what the data file holds, which differs
+/// from `manager` when startup changes were kept in memory for `manual_save`.
+pub fn run(
+    mut manager: TaskManager,
+    mut on_disk: TaskManager,
+    mut config: Config,
+    config_file: &Path,
+    data_file: &Path,
+) -> Result<()> {
+    catch_interrupts();
+    // Set while the data file lags behind, after a failed save or, with
+    // manual_save, any change. `on_disk` is what it last held, for the Save
+    // commit message.
+    let mut unsaved = !same_state(&on_disk, &manager);
+    let session_start = manager.clone();
+    // Ids of the tasks changed this session, most recent first.
+    let mut recent: Vec<usize> = Vec::new();
+    // The list before each change, newest last, and the states undone since.
+    let mut undo: Vec<TaskManager> = Vec::new();
+    let mut redo: Vec<TaskManager> = Vec::new();
+    // The archive suggestion comes up at most once a session.
+    let mut suggested = manager.tasks.len() > config.task_limit();
+    if suggested && suggest_archive(&mut manager, &config, data_file)? {
+        unsaved = !save_and_record(&manager, &config, data_file, "archive finished tasks")?;
+    }
+    loop {
+        let entries: Vec<usize> = (0..MENU.len())
+            .filter(|&index| !config.read_only || MENU[index].2)
+            .collect();
+        let choices: Vec<(char, &str)> = entries
+            .iter()
+            .map(|&index| (MENU[index].0, tr(MENU[index].1)))
+            .collect();
+
+        let stats = manager.stats(today());
+        let mut prompt = format!(
+            "{} ({} {}, {} {})",
+            tr("What would you like to do?"),
+            stats.open,
+            tr("open"),
+            stats.overdue,
+            tr("overdue")
+        );
+        if config.read_only {
+            prompt = format!("{} {}", prompt, tr("[read-only]").yellow());
+        } else if unsaved && config.manual_save {
+            prompt = format!("{} {}", prompt, tr("[unsaved]").yellow());
+        }
+        // Leaving the menu with Esc or Ctrl-C exits as if Exit was picked.
+        let selection = match shortcut_select(&prompt, &choices, 0)? {
+            Some(index) => entries[index],
+            None => EXIT,
+        };
+
+        if selection == SESSION_CHANGES {
+            print_changes(&session_start, &manager);
+            continue;
+        }
+        if selection == SAVE {
+            let changes = diff(&on_disk, &manager);
+            let action = if changes.is_empty() { "save".to_string() } else { changes.summary() };
+            unsaved = !save_and_record(&manager, &config, data_file, &action)?;
+            if !unsaved {
+                on_disk = manager.clone();
+                println!("{}", tr("Saved.").green());
+            }
+            continue;
+        }
+        if selection == RELOAD {
+            if unsaved {
+                let discard = Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt(tr("Discard the unsaved changes?"))
+                    .default(false)
+                    .interact();
+                if !matches!(discard, Ok(true)) {
+                    continue;
+                }
+            }
+            match TaskManager::load(data_file) {
+                Ok(loaded) => {
+                    manager = loaded;
+                    config.file_tags = manager.tag_meta.clone();
+                    on_disk = manager.clone();
+                    unsaved = false;
+                    // Undoing past a reload would bring back what was discarded.
+                    undo.clear();
+                    redo.clear();
+                    println!("{} ({})", tr("Reloaded from disk").green(), data_file.display());
+                }
+                Err(e) => println!("{}", format!("{:#}", e).red()),
+            }
+            continue;
+        }
+
+        if selection == EDIT_DATA_FILE && unsaved {
+            // The editor opens the file, so it has to hold the changes first.
+            let save = Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt(tr("Save your changes before editing the file?"))
+                .default(true)
+                .interact();
+            if !matches!(save, Ok(true)) {
+                println!("{}", tr("Cancelled.").yellow());
+                continue;
+            }
+            let changes = diff(&on_disk, &manager);
+            let action = if changes.is_empty() { "save".to_string() } else { changes.summary() };
+            unsaved = !save_and_record(&manager, &config, data_file, &action)?;
+            if unsaved {
+                continue;
+            }
+            on_disk = manager.clone();
+        }
+
+        let before = manager.clone();
+        let result = if selection == UNDO || selection == REDO {
+            let (from, to, done, none) = if selection == UNDO {
+                (&mut undo, &mut redo, "Undone.", "Nothing to undo.")
+            } else {
+                (&mut redo, &mut undo, "Redone.", "Nothing to redo.")
+            };
+            match from.pop() {
+                Some(state) => {
+                    push_bounded(to, std::mem::replace(&mut manager, state));
+                    config.file_tags = manager.tag_meta.clone();
+                    println!("{}", tr(done).green());
+                    Ok(Flow::Save)
+                }
+                None => {
+                    println!("{}", tr(none).yellow());
+                    Ok(Flow::Skip)
+                }
+            }
+        } else if selection == RECENT {
+            act_on_recent(&mut manager, &config, &recent)
+        } else {
+            perform(selection, &mut manager, &mut config, config_file, data_file)
+        };
+        let changes = diff(&before, &manager);
+        // A new change starts a new branch, so what was undone can't come back.
+        let stepped = selection == UNDO || selection == REDO;
+        if !stepped && matches!(result, Ok(Flow::Save)) && !same_state(&before, &manager) {
+            push_bounded(&mut undo, before);
+            redo.clear();
+        }
+        let changed = changes.added.iter().chain(&changes.completed).chain(&changes.edited);
+        for task in changed {
+            recent.retain(|id| *id != task.id);
+            recent.insert(0, task.id);
+        }
+        recent.truncate(RECENT_LIMIT);
+
+        // The edited file is what's on disk now.
+        if selection == EDIT_DATA_FILE && matches!(result, Ok(Flow::Save)) {
+            on_disk = manager.clone();
+        }
+        match result {
+            Ok(Flow::Save) if config.read_only => {}
+            Ok(Flow::Save) if config.manual_save => unsaved = !same_state(&on_disk, &manager),
+            Ok(Flow::Save) => {
+                let action = if changes.is_empty() {
+                    MENU[selection].1.to_lowercase()
+                } else {
+                    changes.summary()
+                };
+                unsaved = !save_and_record(&manager, &config, data_file, &action)?;
+                if !suggested && manager.tasks.len() > config.task_limit() {
+                    suggested = true;
+                    if suggest_archive(&mut manager, &config, data_file)? {
+                        let action = "archive finished tasks";
+                        unsaved = !save_and_record(&manager, &config, data_file, action)?;
+                    }
+                }
+                if !unsaved {
+                    on_disk = manager.clone();
+                }
+            }
+            Ok(Flow::Skip) => {}
+            Ok(Flow::Exit) => {
+                if !diff(&session_start, &manager).is_empty() {
+                    print_changes(&session_start, &manager);
+                }
+                let save = if unsaved && config.manual_save {
+                    let answer = Confirm::with_theme(&ColorfulTheme::default())
+                        .with_prompt(tr("Save your changes before leaving?"))
+                        .default(true)
+                        .interact();
+                    match answer {
+                        Ok(save) => save,
+                        // Cancelling the question cancels leaving.
+                        Err(_) => {
+                            println!("\n{}", tr("Cancelled.").yellow());
+                            continue;
+                        }
+                    }
+                } else {
+                    unsaved
+                };
+                if save {
+                    save_and_record(&manager, &config, data_file, "save on exit")?;
+                }
+                break;
+            }
+            Err(e) if is_interrupted(&e) => {
+                let _ = Term::stdout().show_cursor();
+                println!("\n{}", tr("Cancelled.").yellow());
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,384 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use crate::i18n::{tr, Lang};
+use crate::manager::read_text_file;
+use crate::task::{normalize_tag, Priority, TagMeta};
+
+/// How listings are ordered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Id,
+    /// `cmp_tasks`: open tasks first, then by due date, priority and id.
+    DueDate,
+    /// Most recently changed first, ties in `cmp_tasks` order.
+    Updated,
+    /// The order kept in the task file, set with Move to Position.
+    Manual,
+}
+
+impl SortOrder {
+    /// Every sort order, in menu order.
+    pub const ALL: [SortOrder; 4] =
+        [SortOrder::Id, SortOrder::DueDate, SortOrder::Updated, SortOrder::Manual];
+
+    /// Reads a sort order as given on the command line.
+    pub fn parse(name: &str) -> Option<SortOrder> {
+        match name.to_lowercase().as_str() {
+            "id" => Some(SortOrder::Id),
+            "due" | "due_date" => Some(SortOrder::DueDate),
+            "updated" => Some(SortOrder::Updated),
+            "manual" => Some(SortOrder::Manual),
+            _ => None,
+        }
+    }
+
+    /// The sort order's English name, as shown in menus.
+    pub fn label(self) -> &'static str {
+        match self {
+            SortOrder::Id => "ID",
+            SortOrder::DueDate => "Due date",
+            SortOrder::Updated => "Recently updated",
+            SortOrder::Manual => "Manual",
+        }
+    }
+}
+
+/// The day weeks start on in the calendar and weekly views.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeekStart {
+    #[default]
+    Monday,
+    Sunday,
+}
+
+impl WeekStart {
+    /// The weekday this setting names.
+    pub fn weekday(self) -> Weekday {
+        match self {
+            WeekStart::Monday => Weekday::Mon,
+            WeekStart::Sunday => Weekday::Sun,
+        }
+    }
+
+    /// The first day of the week containing `day`.
+    pub fn first_day(self, day: NaiveDate) -> NaiveDate {
+        day.week(self.weekday()).first_day()
+    }
+}
+
+/// What to do with a description longer than `max_description_length`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LengthPolicy {
+    #[default]
+    Reject,
+    Truncate,
+}
+
+/// How tags show in listings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagStyle {
+    /// `[home, work]`
+    #[default]
+    Brackets,
+    /// `#home #work`
+    Hash,
+    /// `+home +work`
+    Plus,
+}
+
+impl TagStyle {
+    /// The tags joined in this style.
+    pub fn render(self, tags: &[String]) -> String {
+        self.render_with(tags, str::to_string)
+    }
+
+    /// Like `render`, with each tag name passed through `paint` first.
+    pub fn render_with(self, tags: &[String], paint: impl Fn(&str) -> String) -> String {
+        let painted = tags.iter().map(|t| paint(t));
+        match self {
+            TagStyle::Brackets => format!("[{}]", painted.collect::<Vec<_>>().join(", ")),
+            TagStyle::Hash => painted.map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" "),
+            TagStyle::Plus => painted.map(|t| format!("+{}", t)).collect::<Vec<_>>().join(" "),
+        }
+    }
+}
+
+/// How `list_tasks` picks and orders the tasks it shows.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct View {
+    pub sort: SortOrder,
+    pub filter: Option<String>,
+    /// Only tasks carrying this tag, on top of `filter`.
+    pub tag: Option<String>,
+    /// Only tasks of this priority or higher.
+    pub min_priority: Option<Priority>,
+    pub hide_completed: bool,
+    /// Show the someday/maybe tasks instead of the regular ones.
+    pub someday: bool,
+    /// Only the open tasks waiting on someone.
+    pub waiting: bool,
+}
+
+/// Settings, read from `config.json` in the data directory.
+///
+/// With a list open (`--list <name>`), the entries under `lists.<name>` take
+/// precedence over the top-level ones, which in turn take precedence over the
+/// defaults. Each entry replaces the setting of the same name as a whole, so
+/// `"view": {"hide_completed": true}` resets the rest of the view. Changing an
+/// overridden setting from the menu updates the list's entry and leaves the
+/// top-level one alone.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Ask for confirmation before adding a tag that no task uses yet.
+    pub confirm_new_tags: bool,
+    /// The last listing view, restored on startup.
+    pub view: View,
+    /// Days from today to default the due date to when a task carries the tag.
+    pub tag_due_offsets: HashMap<String, i64>,
+    /// Tags added automatically to new tasks whose description contains the
+    /// keyword, ignoring case, e.g. `{"email": "comms"}`.
+    pub auto_tags: HashMap<String, String>,
+    /// Alternative spellings of tags, e.g. `{"wip": "in-progress"}`. Aliases
+    /// are stored as the canonical tag, and either form finds both in search.
+    pub tag_aliases: HashMap<String, String>,
+    /// Color the whole row of overdue (red) and due-today (yellow) tasks
+    /// rather than just the status marker.
+    pub highlight_overdue_rows: bool,
+    /// Wrap long descriptions to the terminal width, continuing under the
+    /// description rather than running past the edge.
+    pub wrap_descriptions: bool,
+    /// Leave out the dim "(no date)" shown after open tasks without a due date.
+    pub hide_no_date_marker: bool,
+    pub tag_style: TagStyle,
+    /// Color names for tags in listings, e.g. `{"work": "bright blue"}`.
+    /// Other tags get a color picked from their name, the same every run.
+    pub tag_colors: HashMap<String, String>,
+    /// Columns to preselect when importing a CSV file, by task field, e.g.
+    /// `{"description": "Title", "due_date": "Deadline"}`. Columns named
+    /// like the field are picked otherwise.
+    pub csv_import_columns: HashMap<String, String>,
+    /// Leave tags out of listings; Task Details still shows them.
+    pub hide_tags: bool,
+    /// Longest description accepted, in characters. Unlimited when unset.
+    pub max_description_length: Option<usize>,
+    pub description_length_policy: LengthPolicy,
+    /// Permanently remove trashed tasks this many days after deletion, on
+    /// startup. Trash is kept until emptied when unset.
+    pub trash_retention_days: Option<i64>,
+    /// Keep changes in memory until Save is picked from the menu, rather than
+    /// saving after every action; Reload from Disk drops them. Exit asks
+    /// before leaving unsaved changes behind.
+    pub manual_save: bool,
+    /// Commit the task file to a git repository in the data directory after
+    /// every save from the menu, creating the repository if needed. A project
+    /// repository the directory sits in is left alone; see `git::commit_file`.
+    pub git_history: bool,
+    /// When adding or editing a task, offer to move a due date that falls
+    /// on a weekend to the following Monday.
+    pub warn_weekend_due: bool,
+    /// Priority preselected when adding a task.
+    pub default_priority: Priority,
+    /// Soft limit on the number of tasks in the file; past it PlanSync
+    /// suggests archiving the finished ones. 500 when unset.
+    pub max_tasks: Option<usize>,
+    /// Bulk actions on more tasks than this ask for confirmation first; 5
+    /// when unset.
+    pub bulk_confirm_threshold: Option<usize>,
+    /// Days covered by the completion report; 14 when unset.
+    pub report_days: Option<usize>,
+    /// Where weekly figures start counting.
+    pub week_start: WeekStart,
+    /// Length of a pomodoro in minutes; 25 when unset.
+    pub pomodoro_minutes: Option<u64>,
+    /// Length of the break after a pomodoro in minutes; 5 when unset.
+    pub break_minutes: Option<u64>,
+    /// Minutes of estimated work the Today Plan fits into a day; 480 when
+    /// unset.
+    pub daily_capacity_minutes: Option<u32>,
+    /// Days past due after which Lint flags an open task; 14 when unset.
+    pub lint_overdue_days: Option<i64>,
+    /// Days without a change after which Lint flags an open task; 30 when
+    /// unset.
+    pub lint_stale_days: Option<i64>,
+    /// When the interactive menu was last started, for the welcome message.
+    pub last_opened: Option<DateTime<Utc>>,
+    /// Lowest priority the welcome message counts overdue tasks for; the
+    /// Overdue view still shows them all. Every priority when unset.
+    pub notify_min_priority: Option<Priority>,
+    /// Interface language; `--lang` overrides it for a single run.
+    pub lang: Lang,
+    /// Per-list settings, keyed by list name, in the same form as the ones above.
+    pub lists: HashMap<String, Map<String, Value>>,
+    /// The list opened with `--list`, whose overrides are applied. Never saved.
+    #[serde(skip)]
+    pub list: Option<String>,
+    /// Top-level values of the settings the open list overrides, so saving
+    /// can put them back. Never saved.
+    #[serde(skip)]
+    global: Map<String, Value>,
+    /// Set by `--quiet` for the non-interactive commands; never saved.
+    #[serde(skip)]
+    pub quiet: bool,
+    /// Set by `--read-only`: nothing may write the task file, and the config
+    /// isn't saved either, so view changes last only for the session.
+    #[serde(skip)]
+    pub read_only: bool,
+    /// The task file's `tag_meta`, consulted after `tag_aliases` and
+    /// `tag_colors`. Copied in whenever the file is read; never saved here.
+    #[serde(skip)]
+    pub file_tags: BTreeMap<String, TagMeta>,
+}
+
+impl Config {
+    /// Reads the config, applying the overrides of `list` when given.
+    pub fn load(path: &Path, list: Option<&str>) -> Result<Self> {
+        let mut settings = if path.exists() {
+            let contents = read_text_file(path)?;
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Invalid config file {}", path.display()))?
+        } else {
+            Map::new()
+        };
+
+        let overrides = list
+            .and_then(|list| settings.get("lists")?.get(list)?.as_object())
+            .cloned()
+            .unwrap_or_default();
+        let mut global = Map::new();
+        for (key, value) in overrides {
+            if let Some(old) = settings.insert(key.clone(), value) {
+                global.insert(key, old);
+            }
+        }
+
+        let mut config: Config = serde_json::from_value(Value::Object(settings))
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        config.list = list.map(str::to_string);
+        config.global = global;
+        Ok(config)
+    }
+
+    /// Writes the config back, keeping the open list's overrides in its own
+    /// entry. Does nothing in read-only mode.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        let Value::Object(mut settings) = serde_json::to_value(self)? else {
+            unreachable!("Config serializes to an object");
+        };
+        if let Some(list) = &self.list
+            && let Some(overrides) = self.lists.get(list)
+        {
+            let mut current = Map::new();
+            for key in overrides.keys() {
+                if let Some(value) = settings.remove(key) {
+                    current.insert(key.clone(), value);
+                }
+                if let Some(old) = self.global.get(key) {
+                    settings.insert(key.clone(), old.clone());
+                }
+            }
+            if let Some(Value::Object(lists)) = settings.get_mut("lists") {
+                lists.insert(list.clone(), Value::Object(current));
+            }
+        }
+        let contents = serde_json::to_string_pretty(&settings)?;
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// `max_tasks`, or 500 when unset.
+    pub fn task_limit(&self) -> usize {
+        self.max_tasks.unwrap_or(500)
+    }
+
+    /// `bulk_confirm_threshold`, or 5 when unset.
+    pub fn bulk_threshold(&self) -> usize {
+        self.bulk_confirm_threshold.unwrap_or(5)
+    }
+
+    /// `daily_capacity_minutes`, or 480 when unset.
+    pub fn daily_capacity(&self) -> u32 {
+        self.daily_capacity_minutes.unwrap_or(480)
+    }
+
+    /// `raw` normalized as a tag, with any alias replaced by its canonical tag.
+    pub fn canonical_tag(&self, raw: &str) -> Option<String> {
+        let tag = normalize_tag(raw)?;
+        let is_tag = |alias: &String| normalize_tag(alias).as_ref() == Some(&tag);
+        let configured = self
+            .tag_aliases
+            .iter()
+            .find(|(alias, _)| is_tag(alias))
+            .and_then(|(_, canonical)| normalize_tag(canonical));
+        configured
+            .or_else(|| {
+                let (name, _) = self.file_tags.iter().find(|(_, m)| m.aliases.iter().any(is_tag))?;
+                Some(name.clone())
+            })
+            .or(Some(tag))
+    }
+
+    /// The task file's metadata for `tag`, aliases resolved.
+    pub fn file_tag(&self, tag: &str) -> Option<&TagMeta> {
+        self.file_tags.get(&self.canonical_tag(tag)?)
+    }
+
+    /// Whether two tags are the same once aliases are resolved.
+    pub fn same_tag(&self, a: &str, b: &str) -> bool {
+        let a = self.canonical_tag(a);
+        a.is_some() && a == self.canonical_tag(b)
+    }
+
+    /// Tags from `auto_tags` whose keyword appears in `description` and that
+    /// `tags` doesn't already hold, normalized and sorted.
+    pub fn auto_tags_for(&self, description: &str, tags: &[String]) -> Vec<String> {
+        let description = description.to_lowercase();
+        let mut extra: Vec<String> = self
+            .auto_tags
+            .iter()
+            .filter(|(keyword, _)| {
+                !keyword.trim().is_empty() && description.contains(&keyword.trim().to_lowercase())
+            })
+            .filter_map(|(_, tag)| self.canonical_tag(tag))
+            .filter(|tag| !tags.contains(tag))
+            .collect();
+        extra.sort();
+        extra.dedup();
+        extra
+    }
+
+    /// The nearest due date implied by `tag_due_offsets` for the given tags,
+    /// whose keys may be spelled any way a tag can. Offsets too large for a
+    /// date are skipped with a warning.
+    pub fn default_due_date(&self, tags: &[String], today: NaiveDate) -> Option<NaiveDate> {
+        self.tag_due_offsets
+            .iter()
+            .filter(|(key, _)| tags.iter().any(|tag| self.same_tag(tag, key)))
+            .filter_map(|(key, days)| {
+                let due = Duration::try_days(*days).and_then(|d| today.checked_add_signed(d));
+                if due.is_none() {
+                    let warning = tr("Ignoring the out-of-range due offset for");
+                    eprintln!("{} '{}': {}", warning, key, days);
+                }
+                due
+            })
+            .min()
+    }
+}
@@ -0,0 +1,81 @@
+use anyhow::{bail, Result};
+
+use crate::task::Task;
+
+/// File formats the `export` command can write.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Markdown,
+}
+
+impl Format {
+    /// Reads a format name as given on the command line.
+    pub fn parse(name: &str) -> Result<Format> {
+        match name.to_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "md" | "markdown" => Ok(Format::Markdown),
+            _ => bail!("Unknown export format '{}' (use csv or md)", name),
+        }
+    }
+
+    /// `tasks` written out in this format.
+    pub fn render(self, tasks: &[&Task]) -> String {
+        match self {
+            Format::Csv => to_csv(tasks),
+            Format::Markdown => to_markdown(tasks),
+        }
+    }
+}
+
+/// Quotes a CSV field when it holds a comma, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// `tasks` as CSV with a header row.
+pub fn to_csv(tasks: &[&Task]) -> String {
+    let mut out = String::from("id,description,status,priority,due_date,tags,notes\n");
+    for task in tasks {
+        let fields = [
+            task.id.to_string(),
+            task.description.clone(),
+            task.status.label().to_lowercase(),
+            task.priority.label().to_lowercase(),
+            task.due_date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+            task.tags.join(";"),
+            task.notes.clone(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Escapes a table cell, keeping it on one line.
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+/// `tasks` as a Markdown table.
+pub fn to_markdown(tasks: &[&Task]) -> String {
+    let mut out = String::from("| ID | Status | Description | Priority | Due | Tags |\n");
+    out.push_str("|---:|---|---|---|---|---|\n");
+    for task in tasks {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            task.id,
+            task.status.label(),
+            markdown_cell(&task.description),
+            task.priority.label(),
+            task.due_date.map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+            markdown_cell(&task.tags.join(", ")),
+        ));
+    }
+    out
+}
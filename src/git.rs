@@ -0,0 +1,88 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use std::{fs, path::Path, process::Command};
+
+use plansync::config::Config;
+use plansync::i18n::tr;
+
+/// Where the history lives when the data directory has no repository of its
+/// own, relative to that directory.
+const GIT_DIR: &str = ".plansync-git";
+
+/// Runs `git` inside `dir` with `repo` selecting the repository, failing with
+/// git's own message when it does. Returns what git printed.
+fn git(dir: &Path, repo: &[&str], args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(repo)
+        .args(args)
+        .output()
+        .context("Could not run git")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("git {} failed: {}", args[0], stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Whether `dir` is the top of a repository that tracks nothing but `name`,
+/// like the ones earlier versions created there. Any other repository around
+/// `dir` belongs to someone else.
+fn own_repository(dir: &Path, name: &str) -> bool {
+    let Ok(top) = git(dir, &[], &["rev-parse", "--show-toplevel"]) else {
+        return false;
+    };
+    let same_dir = match (fs::canonicalize(top.trim()), fs::canonicalize(dir)) {
+        (Ok(top), Ok(dir)) => top == dir,
+        _ => false,
+    };
+    same_dir
+        && git(dir, &[], &["ls-files"]).is_ok_and(|files| files.lines().all(|file| file == name))
+}
+
+/// Commits `file` to the history of its directory: a repository there that
+/// holds only the file, or else one kept in `GIT_DIR`, created first if
+/// needed, so a surrounding project never gets the commits. Does nothing when
+/// the file has no changes to commit.
+pub fn commit_file(file: &Path, message: &str) -> Result<()> {
+    let dir = file.parent().context("The data file has no directory")?;
+    let name = file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("The data file has no usable name")?;
+
+    let repo: &[&str] = if own_repository(dir, name) {
+        &[]
+    } else {
+        if !dir.join(GIT_DIR).exists() {
+            git(dir, &["--git-dir", GIT_DIR], &["init", "-q"])?;
+        }
+        &["--git-dir", GIT_DIR, "--work-tree", "."]
+    };
+    git(dir, repo, &["add", "--", name])?;
+
+    let unchanged = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(repo)
+        .args(["diff", "--cached", "--quiet", "--", name])
+        .status()
+        .context("Could not run git")?
+        .success();
+    if unchanged {
+        return Ok(());
+    }
+    git(dir, repo, &["commit", "-q", "-m", message, "--", name])?;
+    Ok(())
+}
+
+/// With `git_history` on, commits the just-saved `file` as "update: <action>".
+/// A failed commit only warns; the save itself went through.
+pub fn record(file: &Path, config: &Config, action: &str) {
+    if config.git_history
+        && let Err(e) = commit_file(file, &format!("update: {}", action))
+    {
+        eprintln!("{}", format!("{} {:#}", tr("Could not commit the task file:"), e).yellow());
+    }
+}
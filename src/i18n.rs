@@ -0,0 +1,362 @@
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Interface language. English strings double as the lookup keys, so any text
+/// without a translation simply shows in English.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    En,
+    Es,
+}
+
+impl Lang {
+    /// Reads a language code such as `es`.
+    pub fn parse(code: &str) -> Option<Lang> {
+        match code.to_lowercase().as_str() {
+            "en" => Some(Lang::En),
+            "es" => Some(Lang::Es),
+            _ => None,
+        }
+    }
+}
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+/// Picks the language for the rest of the run. Only the first call counts.
+pub fn set_lang(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+/// Translates an English UI string into the current language.
+pub fn tr(text: &'static str) -> &'static str {
+    match LANG.get().copied().unwrap_or_default() {
+        Lang::En => text,
+        Lang::Es => es(text).unwrap_or(text),
+    }
+}
+
+fn es(text: &str) -> Option<&'static str> {
+    Some(match text {
+        // Menu
+        "What would you like to do?" => "¿Qué quieres hacer?",
+        "open" => "abiertas",
+        "overdue" => "vencidas",
+        "[read-only]" => "[solo lectura]",
+        "[unsaved]" => "[sin guardar]",
+        "Add Task" => "Añadir tarea",
+        "List Tasks" => "Listar tareas",
+        "Complete Task" => "Completar tarea",
+        "Delete Task" => "Eliminar tarea",
+        "Edit Task" => "Editar tarea",
+        "Search Tasks" => "Buscar tareas",
+        "Change View" => "Cambiar vista",
+        "Reset View" => "Restablecer vista",
+        "Save Listing to File" => "Guardar listado en archivo",
+        "Edit Data File" => "Editar archivo de datos",
+        "Merge File" => "Combinar archivo",
+        "Restore from Trash" => "Restaurar de la papelera",
+        "Empty Trash" => "Vaciar papelera",
+        "Stats" => "Estadísticas",
+        "Completion Report" => "Informe de completadas",
+        "Focus" => "Enfoque",
+        "Task Details" => "Detalles de la tarea",
+        "Add Comment" => "Añadir comentario",
+        "Attachments" => "Adjuntos",
+        "Someday" => "Algún día",
+        "Move to/from Someday" => "Mover a/desde Algún día",
+        "Set Status" => "Cambiar estado",
+        "Cancel Task" => "Cancelar tarea",
+        "History" => "Historial",
+        "Complete by Tag" => "Completar por etiqueta",
+        "Calendar" => "Calendario",
+        "Session Changes" => "Cambios de la sesión",
+        "Set Color" => "Cambiar color",
+        "Complete Last" => "Completar la última",
+        "Move to Position" => "Mover a una posición",
+        "Import CSV" => "Importar CSV",
+        "Today Plan" => "Plan de hoy",
+        "Waiting On" => "A la espera de",
+        "Waiting" => "En espera",
+        "Lint" => "Revisar la lista",
+        "Recent" => "Recientes",
+        "Save" => "Guardar",
+        "Reload from Disk" => "Recargar del disco",
+        "Save your changes before editing the file?" => {
+            "¿Guardar los cambios antes de editar el archivo?"
+        }
+        "Not an id or range:" => "No es un id ni un rango:",
+        "Range too wide:" => "Rango demasiado amplio:",
+        "Keep the new tag" => "¿Mantener la etiqueta nueva",
+        "Description truncated to characters:" => "Descripción recortada a caracteres:",
+        "Could not save your tasks:" => "No se pudieron guardar las tareas:",
+        "Could not commit the task file:" => "No se pudo confirmar el archivo de tareas:",
+        "Saved listing to" => "Listado guardado en",
+        "Could not read" => "No se pudo leer",
+        "Added:" => "Añadidas:",
+        "updated:" => "actualizadas:",
+        "duplicates skipped:" => "duplicadas omitidas:",
+        "Permanently delete the trashed tasks?" => {
+            "¿Eliminar definitivamente las tareas de la papelera?"
+        }
+        "Permanently deleted tasks:" => "Tareas eliminadas definitivamente:",
+        "Completed per day, last" => "Completadas por día, últimos",
+        "is already done." => "ya está hecha.",
+        "Description is too long" => "La descripción es demasiado larga",
+        "Could not run editor" => "No se pudo abrir el editor",
+        "Editor exited with" => "El editor terminó con",
+        "The file was kept as edited but not reloaded." => {
+            "El archivo se dejó como quedó editado, pero no se recargó."
+        }
+        "Tasks reloaded:" => "Tareas recargadas:",
+        "Could not parse" => "No se pudo interpretar",
+        "does not exist; attaching it anyway" => "no existe; se adjunta de todos modos",
+        "ID" => "ID",
+        "Recently updated" => "Actualizadas recientemente",
+        "Manual" => "Manual",
+        "Row" => "Fila",
+        "invalid due date" => "fecha límite no válida",
+        "can't tell if this means done:" => "no está claro si significa hecha:",
+        "Tasks purged from the trash:" => "Tareas purgadas de la papelera:",
+        "Exported tasks:" => "Tareas exportadas:",
+        "Added task" => "Tarea añadida",
+        "Serving tasks on" => "Sirviendo tareas en",
+        "Ctrl-C to stop" => "Ctrl-C para detener",
+        "Ignoring the out-of-range due offset for" => {
+            "Se ignora el desfase de fecha fuera de rango para"
+        }
+        "Not purging the trash; trash_retention_days is out of range:" => {
+            "No se vacía la papelera; trash_retention_days está fuera de rango:"
+        }
+        "Undo" => "Deshacer",
+        "Redo" => "Rehacer",
+        "Undone." => "Deshecho.",
+        "Redone." => "Rehecho.",
+        "Nothing to undo." => "Nada que deshacer.",
+        "Nothing to redo." => "Nada que rehacer.",
+        "Exit" => "Salir",
+
+        // Prompts
+        "Task description" => "Descripción de la tarea",
+        "A task" => "Ya existe una tarea",
+        "already exists — add anyway?" => "— ¿añadirla de todos modos?",
+        "New description" => "Nueva descripción",
+        "Description can't be empty" => "La descripción no puede estar vacía",
+        "Due date (YYYY-MM-DD or +1d/+1w/+1m) (optional)" => {
+            "Fecha límite (AAAA-MM-DD o +1d/+1w/+1m) (opcional)"
+        }
+        "Due date (YYYY-MM-DD or +1d/+1w/+1m) (leave empty to remove)" => {
+            "Fecha límite (AAAA-MM-DD o +1d/+1w/+1m) (vacío para quitarla)"
+        }
+        "Due date" => "Fecha límite",
+        "Today" => "Hoy",
+        "Tomorrow" => "Mañana",
+        "This weekend" => "Este fin de semana",
+        "Next week" => "La semana que viene",
+        "No date" => "Sin fecha",
+        "Custom…" => "Otra…",
+        "Invalid date. Use YYYY-MM-DD or an offset like +1d, -2d, +1w, +1m" => {
+            "Fecha no válida. Usa AAAA-MM-DD o un desplazamiento como +1d, -2d, +1w, +1m"
+        }
+        "Tags (comma-separated, optional, Tab completes known tags)" => {
+            "Etiquetas (separadas por comas, opcional, Tab completa las conocidas)"
+        }
+        "Tags (comma-separated, Tab completes known tags)" => {
+            "Etiquetas (separadas por comas, Tab completa las conocidas)"
+        }
+        "Task IDs (e.g. 3,5,7-9) or description to complete" => {
+            "IDs (p. ej. 3,5,7-9) o descripción de la tarea a completar"
+        }
+        "Task IDs (e.g. 3,5,7-9) or description to delete" => {
+            "IDs (p. ej. 3,5,7-9) o descripción de la tarea a eliminar"
+        }
+        "Skipping unknown ids:" => "Se omiten los IDs desconocidos:",
+        "Task ID or description to edit" => "ID o descripción de la tarea a editar",
+        "Several tasks match, pick one" => "Varias tareas coinciden, elige una",
+        "Completion note (optional)" => "Nota de cierre (opcional)",
+        "Add a follow-up task?" => "¿Añadir una tarea de seguimiento?",
+        "Search (tag or text; due:overdue, due before 2025-02-01…)" => {
+            "Buscar (etiqueta o texto; due:overdue, due before 2025-02-01…)"
+        }
+        "Search (tag or text, optional)" => "Buscar (etiqueta o texto, opcional)",
+        "Filter (tag or text, optional)" => "Filtro (etiqueta o texto, opcional)",
+        "Sort by" => "Ordenar por",
+        "Lowest priority to show" => "Prioridad mínima a mostrar",
+        "Any" => "Cualquiera",
+        "New position (1 is the top)" => "Nueva posición (1 es la primera)",
+        "Hide completed tasks?" => "¿Ocultar tareas completadas?",
+        "Output file" => "Archivo de salida",
+        "File to merge" => "Archivo a combinar",
+        "CSV file to import" => "Archivo CSV a importar",
+        "Column for" => "Columna para",
+        "(not imported)" => "(no importar)",
+        "Skip exact duplicates?" => "¿Omitir duplicados exactos?",
+        "Task to restore" => "Tarea a restaurar",
+        "Task ID or description to show" => "ID o descripción de la tarea a mostrar",
+        "Task ID or description to comment on" => "ID o descripción de la tarea a comentar",
+        "Comment" => "Comentario",
+        "Tag to complete" => "Etiqueta a completar",
+        "Complete these tasks?" => "¿Completar estas tareas?",
+        "Delete these tasks?" => "¿Eliminar estas tareas?",
+        "Task ID or description to cancel" => "ID o descripción de la tarea a cancelar",
+        "Task ID or description to move" => "ID o descripción de la tarea a mover",
+        "Task ID or description" => "ID o descripción de la tarea",
+        "Add attachment" => "Añadir adjunto",
+        "Open attachment" => "Abrir adjunto",
+        "Remove attachment" => "Quitar adjunto",
+        "File path or URL" => "Ruta de archivo o URL",
+        "Show all comments?" => "¿Mostrar todos los comentarios?",
+        "Waiting on whom? (leave empty to clear)" => {
+            "¿A la espera de quién? (vacío para quitarlo)"
+        }
+        "Estimate in minutes (optional)" => "Estimación en minutos (opcional)",
+        "Enter a whole number of minutes" => "Introduce un número entero de minutos",
+        "is on a weekend" => "cae en fin de semana",
+        "move it to" => "¿moverla al",
+        "Priority" => "Prioridad",
+        "High" => "Alta",
+        "Medium" => "Media",
+        "Low" => "Baja",
+        "Status" => "Estado",
+        "Color" => "Color",
+        "Default" => "Predeterminado",
+        "To do" => "Pendiente",
+        "In progress" => "En curso",
+        "Done" => "Hecha",
+        "Cancelled" => "Cancelada",
+        "Repeat" => "Repetir",
+        "Doesn't repeat" => "No se repite",
+        "Daily" => "A diario",
+        "Weekly" => "Cada semana",
+        "Monthly" => "Cada mes",
+        "Next occurrences:" => "Próximas repeticiones:",
+        "Keep this schedule?" => "¿Mantener esta programación?",
+        "What now?" => "¿Y ahora?",
+        "Recent tasks" => "Tareas recientes",
+        "Mark as done" => "Marcar como hecha",
+        "Start a pomodoro" => "Empezar un pomodoro",
+        "Start a break" => "Empezar un descanso",
+        "Back to menu" => "Volver al menú",
+
+        // Status
+        "Tasks:" => "Tareas:",
+        "(no date)" => "(sin fecha)",
+        "Stats:" => "Estadísticas:",
+        "No tasks yet — add one!" => "Aún no hay tareas — ¡añade una!",
+        "No tasks matched" => "Ninguna tarea coincide con",
+        "matching task(s)" => "tarea(s) coincidente(s)",
+        "No tasks to show in this view." => "No hay tareas que mostrar en esta vista.",
+        "Task not found!" => "¡Tarea no encontrada!",
+        "File not found!" => "¡Archivo no encontrado!",
+        "Completed task" => "Tarea completada",
+        "Completed tasks:" => "Tareas completadas:",
+        "Last task" => "Última tarea",
+        "Next occurrence added as" => "Siguiente repetición añadida como",
+        "No open tasks are tagged" => "Ninguna tarea abierta tiene la etiqueta",
+        "Cancelled task" => "Tarea cancelada",
+        "History:" => "Historial:",
+        "Overdue:" => "Vencidas:",
+        "Nothing is overdue." => "No hay nada vencido.",
+        "day(s) late" => "día(s) de retraso",
+        "Nothing finished yet." => "Aún no hay nada terminado.",
+        "Completion rate" => "Tasa de finalización",
+        "Updated task" => "Tarea actualizada",
+        "Moved to the trash" => "Movida a la papelera",
+        "Moved task" => "Tarea movida",
+        "to position" => "a la posición",
+        "Pick the Manual sort order in Change View to list tasks this way." => {
+            "Elige el orden Manual en Cambiar vista para listar las tareas así."
+        }
+        "Restored task" => "Tarea restaurada",
+        "Moved to Someday" => "Movida a Algún día",
+        "Moved back to active" => "Devuelta a activas",
+        "The trash is empty." => "La papelera está vacía.",
+        "View reset to defaults" => "Vista restablecida",
+        "Cancelled." => "Cancelado.",
+        "Changes this session:" => "Cambios en esta sesión:",
+        "Nothing changed." => "No ha cambiado nada.",
+        "Saved." => "Guardado.",
+        "Discard the unsaved changes?" => "¿Descartar los cambios sin guardar?",
+        "Reloaded from disk" => "Recargado del disco",
+        "Save your changes before leaving?" => "¿Guardar los cambios antes de salir?",
+        "No tasks changed yet this session." => {
+            "Aún no ha cambiado ninguna tarea en esta sesión."
+        }
+        "Your list holds" => "Tu lista tiene",
+        "suggested limit" => "límite sugerido",
+        "Press A to archive" => "Pulsa A para archivar",
+        "finished task(s), or any other key to carry on." => {
+            "tarea(s) terminadas, o cualquier otra tecla para seguir."
+        }
+        "Archived" => "Archivadas",
+        "The file has no header row." => "El archivo no tiene fila de encabezado.",
+        "Imported" => "Importadas",
+        "skipped without a description:" => "omitidas sin descripción:",
+        "with errors:" => "con errores:",
+        "← previous month, → next month, any other key to go back" => {
+            "← mes anterior, → mes siguiente, cualquier otra tecla para volver"
+        }
+        "Auto-tagged:" => "Etiquetado automático:",
+        "Your changes are not saved yet" => "Tus cambios aún no están guardados",
+        "Retry" => "Reintentar",
+        "Save to another file" => "Guardar en otro archivo",
+        "Continue without saving" => "Seguir sin guardar",
+        "Saved a copy to" => "Copia guardada en",
+        "Keeping the changes in memory for the next save." => {
+            "Los cambios se mantienen en memoria hasta el próximo guardado."
+        }
+        "Welcome back — you were last here" => "Hola de nuevo — tu última visita fue",
+        "earlier today" => "hoy",
+        "yesterday" => "ayer",
+        "days ago" => "días atrás",
+        "task(s) became overdue since" => "tarea(s) han vencido desde entonces",
+        "Today Plan:" => "Plan de hoy:",
+        "Nothing is due today." => "No hay nada para hoy.",
+        "Estimated:" => "Estimado:",
+        "task(s) without an estimate" => "tarea(s) sin estimación",
+        "That's more than fits in a day — consider moving something." => {
+            "Es más de lo que cabe en un día — plantéate mover algo."
+        }
+        "Lint:" => "Revisión:",
+        "Nothing to flag." => "Nada que señalar.",
+        "no tags" => "sin etiquetas",
+        "Tag it so tag searches find it." => "Etiquétala para que las búsquedas la encuentren.",
+        "overdue by" => "vencida hace",
+        "Reschedule it, or move it to Someday." => "Cambia la fecha o muévela a Algún día.",
+        "untouched for" => "sin cambios desde hace",
+        "Check it still matters, or cancel it." => "Comprueba que sigue importando, o cancélala.",
+        "same description as" => "misma descripción que",
+        "Merge the two, or delete one." => "Combina las dos o elimina una.",
+        "high priority but no notes" => "prioridad alta pero sin notas",
+        "Note what done looks like." => "Anota qué significa terminarla.",
+        "Focus:" => "Enfoque:",
+        "Nothing left to do!" => "¡No queda nada por hacer!",
+        "Tracked:" => "Registrado:",
+        "Priority:" => "Prioridad:",
+        "Tags:" => "Etiquetas:",
+        "Repeats:" => "Se repite:",
+        "Estimate:" => "Estimación:",
+        "Waiting on:" => "A la espera de:",
+        "waiting" => "esperando a",
+        "Notes:" => "Notas:",
+        "Attachments:" => "Adjuntos:",
+        "Comments" => "Comentarios",
+        "Added a comment to task" => "Comentario añadido a la tarea",
+        "Pomodoro finished!" => "¡Pomodoro terminado!",
+        "Break" => "Descanso",
+        "Break over." => "Fin del descanso.",
+        "Total" => "Total",
+        "Completed" => "Completadas",
+        "Open" => "Abiertas",
+        "Overdue" => "Vencidas",
+        "Avg overdue" => "Retraso medio",
+        "Oldest overdue" => "Mayor retraso",
+        "Due today" => "Para hoy",
+        "Done this week" => "Hechas esta semana",
+        "Current streak" => "Racha actual",
+        "Longest streak" => "Racha más larga",
+        "day(s)" => "día(s)",
+        _ => return None,
+    })
+}
@@ -0,0 +1,149 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::manager::TaskManager;
+use crate::task::{parse_due_date, today, Status};
+
+/// Splits CSV text into rows of fields. Quoted fields may hold commas, line
+/// breaks and doubled quotes, as `to_csv` writes them. Blank lines are
+/// dropped.
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            // Quotes only open a quoted field at its start.
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|row| row.iter().any(|field| !field.trim().is_empty()));
+    rows
+}
+
+/// Which column, by index, holds each task field. Only the description is
+/// required.
+#[derive(Debug, Clone, Default)]
+pub struct CsvColumns {
+    pub description: usize,
+    pub due_date: Option<usize>,
+    pub tags: Option<usize>,
+    pub completed: Option<usize>,
+}
+
+impl CsvColumns {
+    /// The task fields an import can fill, as named in `csv_import_columns`.
+    pub const FIELDS: [&str; 4] = ["description", "due_date", "tags", "completed"];
+
+    /// The column each of `FIELDS` most likely lives in: the one `mapping`
+    /// names for it, or else one named like the field, ignoring case.
+    pub fn guess(header: &[String], mapping: &HashMap<String, String>) -> [Option<usize>; 4] {
+        let find = |name: &str| {
+            header
+                .iter()
+                .position(|column| column.trim().eq_ignore_ascii_case(name.trim()))
+        };
+        Self::FIELDS.map(|field| {
+            mapping
+                .get(field)
+                .and_then(|name| find(name))
+                .or_else(|| find(field))
+                .or_else(|| (field == "completed").then(|| find("status")).flatten())
+        })
+    }
+}
+
+/// What an import added, skipped and failed to read.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub added: usize,
+    /// Rows without a description.
+    pub skipped: usize,
+    /// One message per row that couldn't be read; those rows are not added.
+    pub errors: Vec<String>,
+}
+
+/// Reads a completed column: yes/no style values, or a status name.
+fn parse_completed(value: &str) -> Option<Status> {
+    let value = value.trim().to_lowercase();
+    if let Some(status) = Status::ALL.iter().find(|s| s.label().to_lowercase() == value) {
+        return Some(*status);
+    }
+    match value.as_str() {
+        "true" | "yes" | "y" | "1" | "x" | "completed" => Some(Status::Done),
+        "false" | "no" | "n" | "0" | "" | "todo" | "open" => Some(Status::Todo),
+        _ => None,
+    }
+}
+
+/// Adds a task for each row, the header row left out. Row numbers in the
+/// errors count the header as row 1, as spreadsheets do.
+pub fn import_rows(
+    manager: &mut TaskManager,
+    rows: &[Vec<String>],
+    columns: &CsvColumns,
+    config: &Config,
+) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    let cell = |row: &[String], column: Option<usize>| {
+        column.and_then(|i| row.get(i)).map(|value| value.trim().to_string()).unwrap_or_default()
+    };
+
+    for (index, row) in rows.iter().enumerate() {
+        let number = index + 2;
+        let description = cell(row, Some(columns.description));
+        if description.is_empty() {
+            report.skipped += 1;
+            continue;
+        }
+
+        let due = cell(row, columns.due_date);
+        let due_date = match parse_due_date(&due, today()) {
+            Some(date) => Some(date),
+            None if due.is_empty() => None,
+            None => {
+                let problem = tr("invalid due date");
+                report.errors.push(format!("{} {}: {} '{}'", tr("Row"), number, problem, due));
+                continue;
+            }
+        };
+        let completed = cell(row, columns.completed);
+        let Some(status) = parse_completed(&completed) else {
+            let problem = tr("can't tell if this means done:");
+            report.errors.push(format!("{} {}: {} '{}'", tr("Row"), number, problem, completed));
+            continue;
+        };
+        let mut tags: Vec<String> = cell(row, columns.tags)
+            .split([';', ','])
+            .filter_map(|tag| config.canonical_tag(tag))
+            .collect();
+        tags.dedup();
+
+        let id = manager.create_task(description, due_date, tags)?;
+        if status != Status::Todo {
+            manager.set_status(id, status);
+        }
+        report.added += 1;
+    }
+    Ok(report)
+}
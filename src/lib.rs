@@ -0,0 +1,30 @@
+//! The PlanSync data model, usable without the interactive menu.
+//!
+//! A [`TaskManager`] holds the task list: load it from a JSON file, add,
+//! complete, delete or edit tasks through its methods, and save it back.
+//! The `PlanSync` binary is a terminal front end built on the same API.
+//!
+//! ```no_run
+//! use plansync::TaskManager;
+//! use std::path::Path;
+//!
+//! let path = Path::new("tasks.json");
+//! let mut manager = TaskManager::load(path)?;
+//! manager.complete_task(1, None);
+//! manager.save(path)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod config;
+pub mod export;
+pub mod i18n;
+pub mod import;
+pub mod lint;
+pub mod manager;
+pub mod query;
+pub mod schema;
+pub mod task;
+
+pub use config::Config;
+pub use manager::TaskManager;
+pub use task::{Priority, Status, Task};
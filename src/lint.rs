@@ -0,0 +1,63 @@
+use chrono::NaiveDate;
+
+use crate::config::Config;
+use crate::i18n::tr;
+use crate::manager::TaskManager;
+use crate::task::{Priority, Task};
+
+/// A hygiene problem with one task, and what to do about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub id: usize,
+    pub problem: String,
+    pub suggestion: &'static str,
+}
+
+/// Looks over the open tasks outside Someday for signs of neglect: no tags,
+/// long overdue, untouched for long, the same description as another open
+/// task, and high priority without notes. Nothing is changed. Findings come
+/// in task order, and in that order for each task.
+pub fn lint(manager: &TaskManager, config: &Config, today: NaiveDate) -> Vec<Finding> {
+    let open: Vec<&Task> = manager
+        .view_tasks(&Default::default(), config, today)
+        .into_iter()
+        .filter(|t| t.status.is_open())
+        .collect();
+    let overdue_days = config.lint_overdue_days.unwrap_or(14);
+    let stale_days = config.lint_stale_days.unwrap_or(30);
+    let days = tr("day(s)");
+
+    let mut findings = Vec::new();
+    let mut flag = |task: &Task, problem: String, suggestion: &'static str| {
+        findings.push(Finding { id: task.id, problem, suggestion });
+    };
+    for task in &open {
+        if task.tags.is_empty() {
+            flag(task, tr("no tags").to_string(), tr("Tag it so tag searches find it."));
+        }
+        if let Some(due) = task.due_date {
+            let late = (today - due).num_days();
+            if late > overdue_days {
+                let problem = format!("{} {} {}", tr("overdue by"), late, days);
+                flag(task, problem, tr("Reschedule it, or move it to Someday."));
+            }
+        }
+        let idle = (today - task.updated_at.date_naive()).num_days();
+        if idle > stale_days {
+            let problem = format!("{} {} {}", tr("untouched for"), idle, days);
+            flag(task, problem, tr("Check it still matters, or cancel it."));
+        }
+        if let Some(first) = open
+            .iter()
+            .find(|other| other.id < task.id && other.same_description(&task.description))
+        {
+            let problem = format!("{} #{}", tr("same description as"), first.id);
+            flag(task, problem, tr("Merge the two, or delete one."));
+        }
+        if task.priority == Priority::High && task.notes.trim().is_empty() {
+            let problem = tr("high priority but no notes").to_string();
+            flag(task, problem, tr("Note what done looks like."));
+        }
+    }
+    findings
+}
@@ -1,285 +1,333 @@
-use anyhow::{Context, Result};
-use chrono::{NaiveDate, Utc};
-use dialoguer::{
-    theme::ColorfulTheme,
-    {Input, Select},
-};
-use serde::{Deserialize, Serialize};
-use std::{
-    fs,
-    path::Path,
-};
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Task {
-    id: usize,
-    description: String,
-    completed: bool,
-    tags: Vec<String>,
-    due_date: Option<NaiveDate>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct TaskManager {
-    tasks: Vec<Task>,
-    next_id: usize,
+mod calendar;
+mod cli;
+mod git;
+mod serve;
+
+use anyhow::{bail, Context, Result};
+use chrono::{Duration, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use plansync::config::{Config, SortOrder, View};
+use plansync::i18n::{self, tr, Lang};
+use plansync::manager::TaskManager;
+use plansync::task::{self, normalize_tag, Priority};
+use plansync::{export, schema};
+
+/// Command-line arguments: an optional subcommand with its parameters, plus
+/// global flags, which may appear anywhere.
+struct Args {
+    command: Option<String>,
+    params: Vec<String>,
+    quiet: bool,
+    read_only: bool,
+    lang: Option<Lang>,
+    sort: Option<SortOrder>,
+    filter: Option<String>,
+    tag: Option<String>,
+    list: Option<String>,
+    /// Data directory given with `--data`, in place of the platform's one.
+    data: Option<PathBuf>,
+    priority: Option<Priority>,
+    port: Option<u16>,
 }
 
-impl TaskManager {
-    fn new() -> Self {
-        TaskManager {
-            tasks: Vec::new(),
-            next_id: 1,
+impl Args {
+    fn parse() -> Result<Self> {
+        let mut args = Args {
+            command: None,
+            params: Vec::new(),
+            quiet: false,
+            read_only: false,
+            lang: None,
+            sort: None,
+            filter: None,
+            tag: None,
+            list: None,
+            data: None,
+            priority: None,
+            port: None,
+        };
+        let mut argv = std::env::args().skip(1);
+        while let Some(arg) = argv.next() {
+            match arg.as_str() {
+                "-q" | "--quiet" => args.quiet = true,
+                "--read-only" => args.read_only = true,
+                "--lang" => {
+                    let code = argv.next().context("--lang needs a language code")?;
+                    let lang = Lang::parse(&code)
+                        .with_context(|| format!("Unsupported language '{}'", code))?;
+                    args.lang = Some(lang);
+                }
+                "--sort" => {
+                    let name = argv.next().context("--sort needs id, due, updated or manual")?;
+                    let sort = SortOrder::parse(&name)
+                        .with_context(|| format!("Unknown sort order '{}'", name))?;
+                    args.sort = Some(sort);
+                }
+                "--filter" => {
+                    args.filter = Some(argv.next().context("--filter needs a tag or text")?);
+                }
+                "--tag" => {
+                    let tag = argv.next().context("--tag needs a tag")?;
+                    let tag = normalize_tag(&tag)
+                        .with_context(|| format!("Invalid tag '{}'", tag))?;
+                    args.tag = Some(tag);
+                }
+                "--data" => {
+                    let dir = argv.next().context("--data needs a directory")?;
+                    args.data = Some(PathBuf::from(dir));
+                }
+                "--priority" => {
+                    let name = argv.next().context("--priority needs high, medium or low")?;
+                    let priority = Priority::parse(&name)
+                        .with_context(|| format!("Unknown priority '{}'", name))?;
+                    args.priority = Some(priority);
+                }
+                "--list" => {
+                    let name = argv.next().context("--list needs a list name")?;
+                    let valid = name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+                    if name.is_empty() || !valid {
+                        bail!("List names may only hold letters, digits, '-' and '_'");
+                    }
+                    args.list = Some(name);
+                }
+                "--port" => {
+                    let port = argv.next().context("--port needs a port number")?;
+                    let port = port
+                        .parse()
+                        .with_context(|| format!("Invalid port '{}'", port))?;
+                    args.port = Some(port);
+                }
+                flag if flag.starts_with('-') => bail!("Unknown option '{}'", flag),
+                _ if args.command.is_none() => args.command = Some(arg),
+                _ => args.params.push(arg),
+            }
         }
+        Ok(args)
     }
+}
 
-    fn add_task(&mut self, task: Task) {
-        self.tasks.push(task);
+/// Where the config file and the open list's directory live, creating the
+/// directory if needed. That is under `--data` when given, else under the
+/// platform's data directory; should the latter be missing or unwritable,
+/// the current directory is used, with a warning saying so.
+fn data_paths(args: &Args) -> Result<(PathBuf, PathBuf)> {
+    // Each named list keeps its tasks, trash and archive in a directory of its own.
+    let list_dir = |dir: &Path| match &args.list {
+        Some(name) => dir.join("lists").join(name),
+        None => dir.to_path_buf(),
+    };
+    let create = |dir: &Path| {
+        fs::create_dir_all(list_dir(dir))
+            .with_context(|| format!("Could not create {}", list_dir(dir).display()))
+    };
+
+    if let Some(dir) = &args.data {
+        create(dir)?;
+        return Ok((dir.join("config.json"), list_dir(dir)));
     }
-
-    fn complete_task(&mut self, id: usize) -> Option<&Task> {
-        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
-            task.completed = true;
-            Some(task)
-        } else {
-            None
+    let default = dirs::data_dir()
+        .context("Could not find the data directory")
+        .map(|dir| dir.join("rust_task_manager"))
+        .and_then(|dir| create(&dir).map(|_| dir));
+    match default {
+        Ok(dir) => Ok((dir.join("config.json"), list_dir(&dir))),
+        Err(e) => {
+            let dir = Path::new(".");
+            create(dir)?;
+            // A plain config.json here could well belong to something else.
+            let config_file = dir.join("plansync-config.json");
+            eprintln!(
+                "Warning: {:#}. Using {} and {} instead; pass --data <dir> to pick a place.",
+                e,
+                list_dir(dir).join("tasks.json").display(),
+                config_file.display()
+            );
+            Ok((config_file, list_dir(dir)))
         }
     }
+}
 
-    fn delete_task(&mut self, id: usize) -> Option<Task> {
-        if let Some(pos) = self.tasks.iter().position(|t| t.id == id) {
-            Some(self.tasks.remove(pos))
-        } else {
-            None
+fn main() -> Result<()> {
+    let args = Args::parse()?;
+
+    let (config_file, list_dir) = data_paths(&args)?;
+    let mut config = Config::load(&config_file, args.list.as_deref())?;
+    i18n::set_lang(args.lang.unwrap_or(config.lang));
+    let data_file = list_dir.join("tasks.json");
+
+    // Neither needs the tasks, and edit-file is how a file that won't load
+    // gets fixed.
+    match args.command.as_deref() {
+        Some("schema") => {
+            println!("{}", serde_json::to_string_pretty(&schema::task_file_schema())?);
+            return Ok(());
         }
-    }
-
-    fn edit_task(&mut self, id: usize) -> Option<&Task> {
-        if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
-            let description: String = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt("New description")
-                .default(task.description.clone())
-                .interact()
-                .unwrap();
-
-            let due_date = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt("Due date (YYYY-MM-DD) (leave empty to remove)")
-                .allow_empty(true)
-                .validate_with(|input: &String| {
-                    if input.is_empty() {
-                        return Ok(());
-                    }
-                    NaiveDate::parse_from_str(input, "%Y-%m-%d")
-                        .map(|_| ())
-                        .map_err(|_| "Invalid date format. Use YYYY-MM-DD".into())
-                })
-                .interact()
-                .unwrap();
-
-            let tags = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt("Tags (comma-separated)")
-                .default(task.tags.join(", "))
-                .interact()
-                .map(|s: String| {
-                    s.split(',')
-                        .map(|tag| tag.trim().to_lowercase())
-                        .filter(|tag| !tag.is_empty())
-                        .collect()
-                })
-                .unwrap();
-
-            task.description = description;
-            task.due_date = due_date
-                .parse::<NaiveDate>()
-                .ok();
-            task.tags = tags;
-            Some(task)
-        } else {
-            None
+        Some("edit-file") if args.read_only => bail!("edit-file is not available with --read-only"),
+        Some("edit-file") => {
+            if !data_file.exists() {
+                TaskManager::new().save(&data_file)?;
+                git::record(&data_file, &config, "create task file");
+            }
+            if cli::edit_data_file(&data_file, args.quiet)?.is_some() {
+                git::record(&data_file, &config, "edit data file");
+            }
+            return Ok(());
         }
+        _ => {}
     }
 
-    fn save(&self, path: &Path) -> Result<()> {
-        let contents = serde_json::to_string_pretty(self)?;
-        fs::write(path, contents)?;
-        Ok(())
-    }
-
-    fn load(path: &Path) -> Result<Self> {
-        if path.exists() {
-            let contents = fs::read_to_string(path)?;
-            let manager = serde_json::from_str(&contents)?;
-            Ok(manager)
-        } else {
-            Ok(TaskManager::new())
+    let mut manager = TaskManager::load(&data_file)?;
+    config.file_tags = manager.tag_meta.clone();
+
+    config.read_only = args.read_only;
+    // With manual_save the purge stays in memory until the user saves.
+    let mut on_disk = manager.clone();
+    let retention = config.trash_retention_days.filter(|_| !config.read_only);
+    let cutoff = retention.and_then(|days| {
+        let cutoff = Duration::try_days(days).and_then(|d| Utc::now().checked_sub_signed(d));
+        if cutoff.is_none() {
+            let warning = tr("Not purging the trash; trash_retention_days is out of range:");
+            eprintln!("{} {}", warning, days);
         }
-    }
-
-    fn list_tasks(&self, filter: Option<&str>) {
-        let today = Utc::now().naive_utc().date();
-
-        println!("\n{}", "Tasks:".bold().underline());
-        for task in &self.tasks {
-            if let Some(filter) = filter {
-                if !task.tags.contains(&filter.to_lowercase()) && 
-                   !task.description.to_lowercase().contains(&filter.to_lowercase()) {
-                    continue;
-                }
+        cutoff
+    });
+    if let Some(cutoff) = cutoff {
+        let purged = manager.purge_trash(cutoff);
+        if purged > 0 {
+            if !config.manual_save {
+                manager.save(&data_file)?;
+                git::record(&data_file, &config, "purge trash");
+                on_disk = manager.clone();
             }
-
-            let status = if task.completed {
-                "[✓]".green()
-            } else {
-                match task.due_date {
-                    Some(due) if due < today => "[!]".red(),
-                    Some(due) if due == today => "[!]".yellow(),
-                    _ => "[ ]".normal(),
-                }
-            };
-
-            let mut parts = vec![
-                status,
-                format!("#{}", task.id).cyan().normal(),
-                task.description.as_str().normal(),
-            ];
-
-            if let Some(due_date) = task.due_date {
-                let due_str = format!("({})", due_date.format("%Y-%m-%d"));
-                let due_display = if due_date < today {
-                    due_str.red()
-                } else if due_date == today {
-                    due_str.yellow()
-                } else {
-                    due_str.normal()
-                };
-                parts.push(due_display);
-            }
-
-            if !task.tags.is_empty() {
-                parts.push(format!("[{}]", task.tags.join(", ")).blue().normal());
+            if !args.quiet {
+                println!("{} {}", tr("Tasks purged from the trash:"), purged);
             }
-
-            println!("{}", parts.join(" "));
         }
-        println!();
     }
-}
-
-fn main() -> Result<()> {
-    let data_dir = dirs::data_dir()
-        .context("Could not find data directory")?
-        .join("rust_task_manager");
-    
-    if !data_dir.exists() {
-        fs::create_dir_all(&data_dir)?;
-    }
-
-    let data_file = data_dir.join("tasks.json");
-    let mut manager = TaskManager::load(&data_file)?;
-
-    loop {
-        let choices = vec![
-            "Add Task",
-            "List Tasks",
-            "Complete Task",
-            "Delete Task",
-            "Edit Task",
-            "Search Tasks",
-            "Exit",
-        ];
-
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("What would you like to do?")
-            .items(&choices)
-            .default(0)
-            .interact()?;
-
-        match selection {
-            0 => {
-                let description: String = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Task description")
-                    .interact()?;
-
-                let due_date: String = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Due date (YYYY-MM-DD) (optional)")
-                    .allow_empty(true)
-                    .validate_with(|input: &String| {
-                        if input.is_empty() {
-                            return Ok(());
-                        }
-                        NaiveDate::parse_from_str(input, "%Y-%m-%d")
-                            .map(|_| ())
-                            .map_err(|_| "Invalid date format. Use YYYY-MM-DD".into())
-                    })
-                    .interact()?;
 
-                let tags: String = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Tags (comma-separated, optional)")
-                    .allow_empty(true)
-                    .interact()?;
+    // --quiet only applies to the non-interactive commands.
+    config.quiet = args.quiet && args.command.is_some();
 
-                let task = Task {
-                    id: manager.next_id,
-                    description,
-                    completed: false,
-                    tags: tags
-                        .split(',')
-                        .map(|s| s.trim().to_lowercase())
-                        .filter(|s| !s.is_empty())
-                        .collect(),
-                    due_date: due_date.parse().ok(),
-                };
-
-                manager.next_id += 1;
-                manager.add_task(task);
+    match args.command.as_deref() {
+        None => {}
+        Some("list") => {
+            let mut view = config.view.clone();
+            if !args.params.is_empty() {
+                view.filter = Some(args.params.join(" "));
             }
-            1 => manager.list_tasks(None),
-            2 => {
-                let task_id: usize = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Task ID to complete")
-                    .interact()?;
-
-                if let Some(task) = manager.complete_task(task_id) {
-                    println!("Completed task #{}: {}", task.id, task.description);
-                } else {
-                    println!("{}", "Task not found!".red());
-                }
+            if let Some(sort) = args.sort {
+                view.sort = sort;
             }
-            3 => {
-                let task_id: usize = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Task ID to delete")
-                    .interact()?;
-
-                if let Some(task) = manager.delete_task(task_id) {
-                    println!("Deleted task #{}: {}", task.id, task.description);
-                } else {
-                    println!("{}", "Task not found!".red());
-                }
+            if args.filter.is_some() {
+                view.filter = args.filter;
             }
-            4 => {
-                let task_id: usize = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Task ID to edit")
-                    .interact()?;
-
-                if let Some(task) = manager.edit_task(task_id) {
-                    println!("Updated task #{}", task.id);
-                } else {
-                    println!("{}", "Task not found!".red());
+            if args.tag.is_some() {
+                view.tag = args.tag;
+            }
+            if args.priority.is_some() {
+                view.min_priority = args.priority;
+            }
+            manager.list_tasks(&view, &config);
+            return Ok(());
+        }
+        Some("export") => {
+            let format = args.params.first().context("Usage: export <csv|md> [file]")?;
+            let format = export::Format::parse(format)?;
+            let view = View {
+                sort: args.sort.unwrap_or(SortOrder::DueDate),
+                filter: args.filter,
+                tag: args.tag,
+                min_priority: args.priority,
+                ..View::default()
+            };
+            let tasks = manager.view_tasks(&view, &config, task::today());
+            let contents = format.render(&tasks);
+
+            match args.params.get(1) {
+                Some(path) => {
+                    fs::write(path, contents)
+                        .with_context(|| format!("Could not write {}", path))?;
+                    if !config.quiet {
+                        println!("{} {} ({})", tr("Exported tasks:"), tasks.len(), path);
+                    }
                 }
+                None => print!("{}", contents),
             }
-            5 => {
-                let filter: String = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Search (tag or text)")
-                    .interact()?;
-
-                manager.list_tasks(Some(&filter));
+            return Ok(());
+        }
+        Some("add") if config.read_only => bail!("add is not available with --read-only"),
+        Some("add") => {
+            let input = args.params.join(" ");
+            let quick = task::parse_quick_add(&input, task::today());
+            if quick.description.is_empty() {
+                bail!("Usage: add <description> [#tag] [due:<date>] [!high|!medium|!low]");
             }
-            6 => break,
-            _ => unreachable!(),
+            let mut tags: Vec<String> =
+                quick.tags.iter().filter_map(|tag| config.canonical_tag(tag)).collect();
+            tags.extend(config.auto_tags_for(&quick.description, &tags));
+            let due_date = quick.due_date.or_else(|| config.default_due_date(&tags, task::today()));
+            let id = manager.create_task(quick.description, due_date, tags)?;
+            let priority = quick.priority.unwrap_or(config.default_priority);
+            let task = manager.set_priority(id, priority).cloned();
+            manager.save(&data_file)?;
+            git::record(&data_file, &config, &format!("added #{}", id));
+            if let Some(task) = task.filter(|_| !config.quiet) {
+                println!("{} #{}: {}", tr("Added task"), task.id, task.description);
+            }
+            return Ok(());
+        }
+        Some("summary") => {
+            // Meant for scripts and chat, so always in English and on one line.
+            let stats = manager.stats(task::today());
+            let next = manager.next_task().map_or("none", |t| t.description.as_str());
+            println!(
+                "Done: {} | Due today: {} | Overdue: {} | Next: {}",
+                stats.completed, stats.due_today, stats.overdue, next
+            );
+            return Ok(());
         }
+        Some("check") => {
+            // 0: nothing overdue, 1: something overdue, 2: only due today.
+            let stats = manager.stats(task::today());
+            let code = if stats.overdue > 0 {
+                1
+            } else if stats.due_today > 0 {
+                2
+            } else {
+                0
+            };
+            if !config.quiet {
+                println!("{} overdue, {} due today", stats.overdue, stats.due_today);
+            }
+            std::process::exit(code);
+        }
+        Some("serve") => {
+            serve::serve(&data_file, args.port.unwrap_or(7878), config.quiet)?;
+            return Ok(());
+        }
+        Some(other) => bail!("Unknown command '{}'", other),
+    }
 
-        manager.save(&data_file)?;
+    if let Some(last) = config.last_opened {
+        let today = task::today();
+        let since = last.date_naive();
+        let when = match (today - since).num_days() {
+            0 => tr("earlier today").to_string(),
+            1 => tr("yesterday").to_string(),
+            days => format!("{} {}", days, tr("days ago")),
+        };
+        let mut message = format!("{} {}", tr("Welcome back — you were last here"), when);
+        let min_priority = config.notify_min_priority.unwrap_or(Priority::Low);
+        let overdue = manager.became_overdue(since, today, min_priority);
+        if overdue > 0 {
+            message = format!("{}; {} {}", message, overdue, tr("task(s) became overdue since"));
+        }
+        println!("{}.", message);
     }
+    config.last_opened = Some(Utc::now());
+    config.save(&config_file)?;
 
-    Ok(())
+    cli::run(manager, on_disk, config, &config_file, &data_file)
 }
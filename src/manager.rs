@@ -0,0 +1,1254 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use colored::{Color, ColoredString, Colorize};
+use console::{measure_text_width, Term};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::Write,
+    fs,
+    path::Path,
+};
+
+use crate::config::{Config, SortOrder, View};
+use crate::i18n::tr;
+use crate::query::parse_date_predicates;
+use crate::task::{
+    cmp_tasks, new_uuid, normalize_tag, single_line, today, Comment, Priority, Recurrence, Status,
+    TagMeta, Task, TimeEntry,
+};
+
+/// Reads a text file such as the task file, the config or a CSV import,
+/// dropping a UTF-8 byte order mark and CRLF line endings that editors on
+/// Windows like to add.
+pub fn read_text_file(path: &Path) -> Result<String> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    let contents = contents.strip_prefix('\u{feff}').unwrap_or(&contents);
+    Ok(contents.replace("\r\n", "\n"))
+}
+
+/// Whether `haystack` contains `needle`, which must already be lowercased.
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    // Lowercasing ASCII keeps every byte in place, so it can be compared
+    // without building a lowered copy.
+    if haystack.is_ascii() {
+        let needle = needle.as_bytes();
+        return needle.is_empty()
+            || haystack.as_bytes().windows(needle.len()).any(|w| w.eq_ignore_ascii_case(needle));
+    }
+    haystack.to_lowercase().contains(needle)
+}
+
+/// Byte ranges of the non-overlapping matches of `needle`, which must already
+/// be lowercased, in `haystack`, ignoring case.
+fn match_ranges(haystack: &str, needle: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    if needle.is_empty() {
+        return ranges;
+    }
+    let mut start = 0;
+    while let Some(c) = haystack[start..].chars().next() {
+        match match_len(&haystack[start..], needle) {
+            Some(len) => {
+                ranges.push((start, start + len));
+                start += len;
+            }
+            None => start += c.len_utf8(),
+        }
+    }
+    ranges
+}
+
+/// Length in bytes of the prefix of `text` that lowercases to `needle`.
+fn match_len(text: &str, needle: &str) -> Option<usize> {
+    let mut lowered = String::new();
+    for (index, c) in text.char_indices() {
+        lowered.extend(c.to_lowercase());
+        if !needle.starts_with(&lowered) {
+            return None;
+        }
+        if lowered.len() == needle.len() {
+            return Some(index + c.len_utf8());
+        }
+    }
+    None
+}
+
+/// `text` with the given byte ranges shown bold and inverted. With colors
+/// off this is just `text`.
+fn highlight_ranges(text: &str, ranges: &[(usize, usize)]) -> String {
+    let mut out = String::new();
+    let mut end = 0;
+    for &(from, to) in ranges {
+        out.push_str(&text[end..from]);
+        out.push_str(&text[from..to].bold().reversed().to_string());
+        end = to;
+    }
+    out.push_str(&text[end..]);
+    out
+}
+
+/// Narrowest column worth wrapping a description into; below it lines just
+/// run on.
+const MIN_WRAP_WIDTH: usize = 20;
+
+/// Columns in the terminal, or 80 when output doesn't go to one.
+fn terminal_width() -> usize {
+    Term::stdout().size_checked().map_or(80, |(_, columns)| columns as usize)
+}
+
+/// Byte ranges of the lines `text` breaks into at whitespace so that none is
+/// wider than `width`, except for single words that are wider on their own.
+fn wrap_lines(text: &str, width: usize) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut word_start = None;
+    for (index, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match word_start {
+            None if !c.is_whitespace() => word_start = Some(index),
+            Some(start) if c.is_whitespace() => {
+                words.push((start, index));
+                word_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    let mut lines: Vec<(usize, usize)> = Vec::new();
+    let mut line_width = 0;
+    for (start, end) in words {
+        let word_width = measure_text_width(&text[start..end]);
+        match lines.last_mut() {
+            Some(line) if line_width + 1 + word_width <= width => {
+                line.1 = end;
+                line_width += 1 + word_width;
+            }
+            _ => {
+                lines.push((start, end));
+                line_width = word_width;
+            }
+        }
+    }
+    if lines.is_empty() {
+        lines.push((0, text.len()));
+    }
+    lines
+}
+
+/// Colors used by the listing. Terminals that advertise truecolor through
+/// `COLORTERM` get RGB shades; everything else gets the basic named colors.
+struct Palette {
+    done: Color,
+    overdue: Color,
+    due_today: Color,
+    id: Color,
+    high: Color,
+    low: Color,
+    in_progress: Color,
+    cancelled: Color,
+}
+
+impl Palette {
+    fn detect() -> Self {
+        let truecolor = std::env::var("COLORTERM")
+            .is_ok_and(|value| value == "truecolor" || value == "24bit");
+        if truecolor {
+            Palette {
+                done: Color::TrueColor { r: 80, g: 200, b: 120 },
+                overdue: Color::TrueColor { r: 235, g: 80, b: 80 },
+                due_today: Color::TrueColor { r: 240, g: 190, b: 60 },
+                id: Color::TrueColor { r: 90, g: 200, b: 220 },
+                high: Color::TrueColor { r: 255, g: 110, b: 200 },
+                low: Color::TrueColor { r: 130, g: 130, b: 130 },
+                in_progress: Color::TrueColor { r: 120, g: 170, b: 255 },
+                cancelled: Color::TrueColor { r: 160, g: 120, b: 120 },
+            }
+        } else {
+            Palette {
+                done: Color::Green,
+                overdue: Color::Red,
+                due_today: Color::Yellow,
+                id: Color::Cyan,
+                high: Color::Magenta,
+                low: Color::BrightBlack,
+                in_progress: Color::Blue,
+                cancelled: Color::BrightBlack,
+            }
+        }
+    }
+}
+
+/// Colors tags are spread over. Red is left out, as it marks overdue tasks.
+const TAG_COLORS: [Color; 10] = [
+    Color::Blue,
+    Color::Cyan,
+    Color::Green,
+    Color::Magenta,
+    Color::Yellow,
+    Color::BrightBlue,
+    Color::BrightCyan,
+    Color::BrightGreen,
+    Color::BrightMagenta,
+    Color::BrightYellow,
+];
+
+/// A color for `tag` picked from its name, the same on every run and
+/// platform (FNV-1a, unlike std's hasher, is fixed).
+pub fn color_for_tag(tag: &str) -> Color {
+    let hash = tag.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    TAG_COLORS[(hash % TAG_COLORS.len() as u64) as usize]
+}
+
+/// The color of `tag` in listings: its entry in `tag_colors` or else the task
+/// file's `tag_meta`, if that names a color, or else `color_for_tag`.
+fn tag_color(tag: &str, config: &Config) -> Color {
+    let configured = config
+        .tag_colors
+        .iter()
+        .find(|(name, _)| config.same_tag(name, tag))
+        .and_then(|(_, color)| color.parse().ok());
+    configured
+        .or_else(|| config.file_tag(tag)?.color.as_ref()?.parse().ok())
+        .unwrap_or_else(|| color_for_tag(tag))
+}
+
+/// Counts for the Stats screen, as of a given day.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub total: usize,
+    pub completed: usize,
+    pub open: usize,
+    /// Kept apart so abandoned tasks don't drag down the completion rate.
+    pub cancelled: usize,
+    pub overdue: usize,
+    /// Days past due, summed over the overdue tasks.
+    pub overdue_days: i64,
+    /// Days past due of the longest overdue task, if any is.
+    pub oldest_overdue: Option<i64>,
+    pub due_today: usize,
+}
+
+impl Stats {
+    /// Share of the finished-or-open tasks that are done, in percent.
+    /// Cancelled tasks count for neither side.
+    pub fn completion_rate(&self) -> f64 {
+        let counted = self.completed + self.open;
+        if counted == 0 {
+            0.0
+        } else {
+            self.completed as f64 * 100.0 / counted as f64
+        }
+    }
+
+    /// Mean days past due of the overdue tasks, or `None` when none are.
+    pub fn avg_overdue(&self) -> Option<f64> {
+        (self.overdue > 0).then(|| self.overdue_days as f64 / self.overdue as f64)
+    }
+}
+
+/// What happened to the tasks between two versions of a list, found by uuid.
+/// Each entry holds the task as it is now, or as it was for deleted ones.
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub added: Vec<Task>,
+    pub completed: Vec<Task>,
+    pub edited: Vec<Task>,
+    /// Trashed, archived or otherwise gone.
+    pub deleted: Vec<Task>,
+}
+
+impl Changes {
+    /// True when the session has changed nothing.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.completed.is_empty()
+            && self.edited.is_empty()
+            && self.deleted.is_empty()
+    }
+
+    /// A one-line account such as "added #5, completed #2 #3".
+    pub fn summary(&self) -> String {
+        let groups = [
+            ("added", &self.added),
+            ("completed", &self.completed),
+            ("edited", &self.edited),
+            ("deleted", &self.deleted),
+        ];
+        groups
+            .iter()
+            .filter(|(_, tasks)| !tasks.is_empty())
+            .map(|(verb, tasks)| {
+                let ids: Vec<String> = tasks.iter().map(|t| format!("#{}", t.id)).collect();
+                format!("{} {}", verb, ids.join(" "))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Compares two versions of a list. Trashed tasks count as gone.
+pub fn diff(before: &TaskManager, after: &TaskManager) -> Changes {
+    let old: HashMap<&str, &Task> = before.active_tasks().map(|t| (t.uuid.as_str(), t)).collect();
+    let new: HashMap<&str, &Task> = after.active_tasks().map(|t| (t.uuid.as_str(), t)).collect();
+
+    let mut changes = Changes::default();
+    for task in after.active_tasks() {
+        match old.get(task.uuid.as_str()) {
+            None => changes.added.push(task.clone()),
+            Some(was) if was.status != Status::Done && task.status == Status::Done => {
+                changes.completed.push(task.clone())
+            }
+            Some(was) if !was.same_content(task) => changes.edited.push(task.clone()),
+            Some(_) => {}
+        }
+    }
+    changes.deleted = before
+        .active_tasks()
+        .filter(|t| !new.contains_key(t.uuid.as_str()))
+        .cloned()
+        .collect();
+    changes
+}
+
+/// What `TaskManager::merge` did with the tasks of the other file.
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    pub added: usize,
+    pub updated: usize,
+    pub skipped: usize,
+}
+
+/// The parts of a task file needed to upgrade tasks that predate `Status`.
+#[derive(Deserialize)]
+struct LegacyFile {
+    tasks: Vec<LegacyTask>,
+}
+
+#[derive(Deserialize)]
+struct LegacyTask {
+    completed: Option<bool>,
+    status: Option<Status>,
+}
+
+/// The task list as stored in the JSON task file. Task ids are handed out
+/// from `next_id` and never reused; deleted tasks stay in `tasks`, marked
+/// as trashed, until the trash is emptied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskManager {
+    pub tasks: Vec<Task>,
+    pub next_id: usize,
+    /// Colors, aliases and descriptions of tags, keyed by normalized tag.
+    /// Left out of the file while empty.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tag_meta: BTreeMap<String, TagMeta>,
+}
+
+impl Default for TaskManager {
+    fn default() -> Self {
+        TaskManager::new()
+    }
+}
+
+impl TaskManager {
+    /// An empty list whose first task gets id 1.
+    pub fn new() -> Self {
+        TaskManager {
+            tasks: Vec::new(),
+            next_id: 1,
+            tag_meta: BTreeMap::new(),
+        }
+    }
+
+    /// Appends a task as is; the caller picks its id.
+    pub fn add_task(&mut self, task: Task) {
+        self.tasks.push(task);
+    }
+
+    /// Hands out `next_id` and moves it on. Should that overflow, which takes
+    /// a hand-edited file, ids restart after the highest one in use.
+    fn take_id(&mut self) -> Result<usize> {
+        let id = self.next_id;
+        match id.checked_add(1) {
+            Some(next) => self.next_id = next,
+            None => {
+                let id = self
+                    .tasks
+                    .iter()
+                    .map(|t| t.id)
+                    .max()
+                    .map_or(Some(1), |max| max.checked_add(1))
+                    .filter(|id| *id < usize::MAX)
+                    .context("No task ids left: a task already uses the highest possible id")?;
+                self.next_id = id + 1;
+                return Ok(id);
+            }
+        }
+        Ok(id)
+    }
+
+    /// Adds a to-do task under the next free id, with every other field at
+    /// its default, and returns the id.
+    pub fn create_task(
+        &mut self,
+        description: String,
+        due_date: Option<NaiveDate>,
+        tags: Vec<String>,
+    ) -> Result<usize> {
+        let id = self.take_id()?;
+        self.add_task(Task {
+            id,
+            description: single_line(&description),
+            status: Status::Todo,
+            tags,
+            due_date,
+            notes: String::new(),
+            priority: Priority::default(),
+            someday: false,
+            recurrence: None,
+            recurrence_day: None,
+            waiting_on: None,
+            estimate_minutes: None,
+            time_entries: Vec::new(),
+            comments: Vec::new(),
+            attachments: Vec::new(),
+            color: None,
+            uuid: new_uuid(),
+            updated_at: Utc::now(),
+            completed_at: None,
+            trashed_at: None,
+        });
+        Ok(id)
+    }
+
+    /// Marks a task done; a repeating one gets its next occurrence added. A
+    /// task that is already done is left as it is, completion time included,
+    /// and gives `None`.
+    pub fn complete_task(&mut self, id: usize, note: Option<&str>) -> Option<&Task> {
+        let task = self.tasks.iter_mut().find(|t| t.id == id)?;
+        if task.status == Status::Done {
+            return None;
+        }
+        let was_open = task.status.is_open();
+        task.status = Status::Done;
+        task.completed_at = Some(Utc::now());
+        task.touch();
+        if let Some(note) = note {
+            if !task.notes.is_empty() {
+                task.notes.push('\n');
+            }
+            task.notes
+                .push_str(&format!("Done {}: {}", today().format("%Y-%m-%d"), note));
+        }
+        if was_open {
+            self.add_next_occurrence(id);
+        }
+        self.get_task(id)
+    }
+
+    /// Adds the occurrence following a repeating task with a due date.
+    fn add_next_occurrence(&mut self, id: usize) -> Option<&Task> {
+        let task = self.tasks.iter().find(|t| t.id == id)?;
+        let due = task.due_date?;
+        let day = task.recurrence_day.unwrap_or(due.day());
+        let due_date = task.recurrence?.next_due(due, day);
+        let task = task.clone();
+        let next = Task {
+            id: self.take_id().ok()?,
+            status: Status::Todo,
+            due_date: Some(due_date),
+            notes: String::new(),
+            waiting_on: None,
+            time_entries: Vec::new(),
+            comments: Vec::new(),
+            uuid: new_uuid(),
+            updated_at: Utc::now(),
+            completed_at: None,
+            trashed_at: None,
+            ..task
+        };
+        self.add_task(next);
+        self.tasks.last()
+    }
+
+    /// Ids of the open tasks carrying `tag` or one of its aliases.
+    pub fn open_with_tag(&self, tag: &str, config: &Config) -> Vec<usize> {
+        self.active_tasks()
+            .filter(|t| t.status.is_open() && t.tags.iter().any(|own| config.same_tag(own, tag)))
+            .map(|t| t.id)
+            .collect()
+    }
+
+    /// Completes every open task carrying `tag` and returns their ids.
+    pub fn complete_by_tag(&mut self, tag: &str, config: &Config) -> Vec<usize> {
+        let ids = self.open_with_tag(tag, config);
+        for &id in &ids {
+            self.complete_task(id, None);
+        }
+        ids
+    }
+
+    /// Moves a task to the trash. It stays in the file until the trash is
+    /// emptied or purged, and can be restored until then.
+    pub fn delete_task(&mut self, id: usize) -> Option<&Task> {
+        let task = self.task_mut(id).filter(|t| t.trashed_at.is_none())?;
+        task.trashed_at = Some(Utc::now());
+        task.touch();
+        Some(task)
+    }
+
+    /// Takes a task back out of the trash.
+    pub fn restore_task(&mut self, id: usize) -> Option<&Task> {
+        let task = self.task_mut(id).filter(|t| t.trashed_at.is_some())?;
+        task.trashed_at = None;
+        task.touch();
+        Some(task)
+    }
+
+    /// Tasks in the trash, in file order.
+    pub fn trashed_tasks(&self) -> Vec<&Task> {
+        self.tasks.iter().filter(|t| t.trashed_at.is_some()).collect()
+    }
+
+    /// Permanently removes every trashed task, returning how many there were.
+    pub fn empty_trash(&mut self) -> usize {
+        let before = self.tasks.len();
+        self.tasks.retain(|t| t.trashed_at.is_none());
+        before - self.tasks.len()
+    }
+
+    /// Permanently removes tasks trashed before `cutoff`.
+    pub fn purge_trash(&mut self, cutoff: DateTime<Utc>) -> usize {
+        let before = self.tasks.len();
+        self.tasks.retain(|t| t.trashed_at.is_none_or(|at| at >= cutoff));
+        before - self.tasks.len()
+    }
+
+    /// Every task that is not in the trash.
+    pub fn active_tasks(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter().filter(|t| t.trashed_at.is_none())
+    }
+
+    /// Moves a task to the 1-based `position` among the tasks outside the
+    /// trash, in the manual order. Positions past either end go to that end.
+    /// Returns the position the task ended up at.
+    pub fn move_to_position(&mut self, id: usize, position: usize) -> Option<usize> {
+        let from = self.tasks.iter().position(|t| t.id == id && t.trashed_at.is_none())?;
+        let task = self.tasks.remove(from);
+        let active: Vec<usize> = (0..self.tasks.len())
+            .filter(|&index| self.tasks[index].trashed_at.is_none())
+            .collect();
+        let position = position.clamp(1, active.len() + 1);
+        let to = active.get(position - 1).copied().unwrap_or(self.tasks.len());
+        self.tasks.insert(to, task);
+        Some(position)
+    }
+
+    /// An open task already described as `description`, which adding it
+    /// again would duplicate.
+    pub fn find_duplicate(&self, description: &str) -> Option<&Task> {
+        self.active_tasks()
+            .find(|t| t.status.is_open() && t.same_description(description))
+    }
+
+    /// The most recently created task outside the trash.
+    pub fn last_added(&self) -> Option<&Task> {
+        self.active_tasks().max_by_key(|t| t.id)
+    }
+
+    /// The task with this id, trashed or not.
+    pub fn get_task(&self, id: usize) -> Option<&Task> {
+        self.tasks.iter().find(|t| t.id == id)
+    }
+
+    fn task_mut(&mut self, id: usize) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|t| t.id == id)
+    }
+
+    // The setters below change one field, mark the task updated and return
+    // it, or `None` when no task has the id.
+
+    /// Line breaks and tabs in the description become spaces.
+    pub fn set_description(&mut self, id: usize, description: String) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        task.description = single_line(&description);
+        task.touch();
+        Some(task)
+    }
+
+    /// Sets or clears the due date of task `id`.
+    pub fn set_due_date(&mut self, id: usize, due_date: Option<NaiveDate>) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        // A new date sets the day a monthly task comes back on.
+        if task.due_date != due_date && task.recurrence.is_some() {
+            task.recurrence_day = due_date.map(|due| due.day());
+        }
+        task.due_date = due_date;
+        task.touch();
+        Some(task)
+    }
+
+    /// Monthly repeats fall on the day of the current due date.
+    pub fn set_recurrence(&mut self, id: usize, recurrence: Option<Recurrence>) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        task.recurrence = recurrence;
+        task.recurrence_day = recurrence.and(task.due_date).map(|due| due.day());
+        task.touch();
+        Some(task)
+    }
+
+    /// Replaces the tags of task `id`.
+    pub fn set_tags(&mut self, id: usize, tags: Vec<String>) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        task.tags = tags;
+        task.touch();
+        Some(task)
+    }
+
+    /// Changes the priority of task `id`.
+    pub fn set_priority(&mut self, id: usize, priority: Priority) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        task.priority = priority;
+        task.touch();
+        Some(task)
+    }
+
+    /// Sets or clears the estimate of task `id`, in minutes.
+    pub fn set_estimate(&mut self, id: usize, minutes: Option<u32>) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        task.estimate_minutes = minutes;
+        task.touch();
+        Some(task)
+    }
+
+    /// Adds a time entry from `started_at` to `ended_at` to task `id`.
+    pub fn log_time(
+        &mut self,
+        id: usize,
+        started_at: DateTime<Utc>,
+        ended_at: DateTime<Utc>,
+    ) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        task.time_entries.push(TimeEntry { started_at, ended_at });
+        task.touch();
+        Some(task)
+    }
+
+    /// Sets the status, keeping `completed_at` in step: it is stamped when the
+    /// task becomes `Done` and cleared when it leaves it.
+    pub fn set_status(&mut self, id: usize, status: Status) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        let repeats = status == Status::Done && task.status.is_open();
+        task.completed_at = match status {
+            Status::Done if task.status == Status::Done => task.completed_at,
+            Status::Done => Some(Utc::now()),
+            _ => None,
+        };
+        task.status = status;
+        task.touch();
+        if repeats {
+            self.add_next_occurrence(id);
+        }
+        self.get_task(id)
+    }
+
+    /// Sets or clears the color of task `id`.
+    pub fn set_color(&mut self, id: usize, color: Option<String>) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        task.color = color;
+        task.touch();
+        Some(task)
+    }
+
+    /// Records who the task waits on; a blank name clears it.
+    pub fn set_waiting_on(&mut self, id: usize, name: &str) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        let name = single_line(name);
+        task.waiting_on = (!name.is_empty()).then_some(name);
+        task.touch();
+        Some(task)
+    }
+
+    /// Moves a task into the someday/maybe list, or back out of it.
+    pub fn toggle_someday(&mut self, id: usize) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        task.someday = !task.someday;
+        task.touch();
+        Some(task)
+    }
+
+    /// Appends a comment, stamped now, to task `id`.
+    pub fn add_comment(&mut self, id: usize, text: String) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        task.comments.push(Comment {
+            text,
+            created_at: Utc::now(),
+        });
+        task.touch();
+        Some(task)
+    }
+
+    /// Attaches a path or URL to task `id`.
+    pub fn add_attachment(&mut self, id: usize, target: String) -> Option<&Task> {
+        let task = self.task_mut(id)?;
+        task.attachments.push(target);
+        task.touch();
+        Some(task)
+    }
+
+    /// Removes the attachment at `index`, returning it.
+    pub fn remove_attachment(&mut self, id: usize, index: usize) -> Option<String> {
+        let task = self.task_mut(id)?;
+        if index >= task.attachments.len() {
+            return None;
+        }
+        task.touch();
+        Some(task.attachments.remove(index))
+    }
+
+    /// The open task to work on next, leaving out ones waiting on someone:
+    /// highest priority first, then the earliest due date (undated tasks
+    /// last), then the lowest id.
+    pub fn next_task(&self) -> Option<&Task> {
+        self.active_tasks()
+            .filter(|t| t.status.is_open() && !t.someday)
+            .filter(|t| t.waiting_on.is_none())
+            .min_by_key(|t| (std::cmp::Reverse(t.priority), t.due_date.is_none(), t.due_date, t.id))
+    }
+
+    /// Ids of the tasks matching `query`. An exact id wins outright; otherwise
+    /// both id prefixes and description substrings count as matches.
+    pub fn find_matches(&self, query: &str) -> Vec<usize> {
+        let query = query.trim();
+        let digits = query.bytes().all(|b| b.is_ascii_digit());
+        if let Ok(id) = query.parse::<usize>()
+            && self.active_tasks().any(|t| t.id == id)
+        {
+            return vec![id];
+        }
+
+        let needle = query.to_lowercase();
+        self.active_tasks()
+            .filter(|t| {
+                (digits && t.id.to_string().starts_with(query))
+                    || contains_ignore_case(&t.description, &needle)
+            })
+            .map(|t| t.id)
+            .collect()
+    }
+
+    /// Totals over the tasks outside the trash, as of `today`.
+    pub fn stats(&self, today: NaiveDate) -> Stats {
+        let mut stats = Stats::default();
+        for task in self.active_tasks() {
+            stats.total += 1;
+            match task.status {
+                Status::Done => {
+                    stats.completed += 1;
+                    continue;
+                }
+                Status::Cancelled => {
+                    stats.cancelled += 1;
+                    continue;
+                }
+                Status::Todo | Status::InProgress => stats.open += 1,
+            }
+            if let Some(due) = task.due_date.filter(|_| task.is_overdue(today)) {
+                let late = (today - due).num_days();
+                stats.overdue += 1;
+                stats.overdue_days += late;
+                stats.oldest_overdue = stats.oldest_overdue.max(Some(late));
+            } else if task.is_due_today(today) {
+                stats.due_today += 1;
+            }
+        }
+        stats
+    }
+
+    /// Open tasks of at least `min_priority` that were not overdue on `since`
+    /// but are by `today`.
+    pub fn became_overdue(
+        &self,
+        since: NaiveDate,
+        today: NaiveDate,
+        min_priority: Priority,
+    ) -> usize {
+        self.active_tasks()
+            .filter(|t| t.is_overdue(today) && t.due_date.is_some_and(|due| due >= since))
+            .filter(|t| t.priority >= min_priority)
+            .count()
+    }
+
+    /// Overdue tasks, the longest overdue first; ties go to the higher
+    /// priority, then the lower id. Someday tasks are left out.
+    pub fn overdue_tasks(&self, today: NaiveDate) -> Vec<&Task> {
+        let mut tasks: Vec<&Task> = self
+            .active_tasks()
+            .filter(|t| t.is_overdue(today) && !t.someday)
+            .collect();
+        tasks.sort_by(|a, b| cmp_tasks(a, b));
+        tasks
+    }
+
+    /// Open tasks due on `today`, highest priority first, then by id. Someday
+    /// tasks are left out.
+    pub fn today_plan(&self, today: NaiveDate) -> Vec<&Task> {
+        let mut tasks: Vec<&Task> = self
+            .active_tasks()
+            .filter(|t| t.is_due_today(today) && !t.someday)
+            .collect();
+        tasks.sort_by(|a, b| cmp_tasks(a, b));
+        tasks
+    }
+
+    /// How many open tasks fall due on each day.
+    pub fn due_counts(&self) -> HashMap<NaiveDate, usize> {
+        let mut counts = HashMap::new();
+        for due in self.active_tasks().filter(|t| t.status.is_open()).filter_map(|t| t.due_date) {
+            *counts.entry(due).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Done and cancelled tasks, most recently changed first.
+    pub fn history(&self) -> Vec<&Task> {
+        let mut tasks: Vec<&Task> = self.active_tasks().filter(|t| !t.status.is_open()).collect();
+        tasks.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then_with(|| cmp_tasks(a, b)));
+        tasks
+    }
+
+    /// Current and longest runs of consecutive days with at least one
+    /// completion. Today only breaks the current streak once it is over, so a
+    /// streak running up to yesterday still counts.
+    pub fn streaks(&self, today: NaiveDate) -> (usize, usize) {
+        let days: BTreeSet<NaiveDate> = self
+            .active_tasks()
+            .filter_map(|t| t.completed_at)
+            .map(|at| at.date_naive())
+            .collect();
+
+        let mut longest = 0;
+        let mut run = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for day in &days {
+            run = match previous {
+                Some(prev) if prev.succ_opt() == Some(*day) => run + 1,
+                _ => 1,
+            };
+            longest = longest.max(run);
+            previous = Some(*day);
+        }
+
+        let mut current = 0;
+        let mut day = if days.contains(&today) {
+            today
+        } else {
+            today.pred_opt().unwrap_or(today)
+        };
+        while days.contains(&day) {
+            current += 1;
+            match day.pred_opt() {
+                Some(prev) => day = prev,
+                None => break,
+            }
+        }
+
+        (current, longest)
+    }
+
+    /// Tasks completed on or after `since`.
+    pub fn completed_since(&self, since: NaiveDate) -> usize {
+        self.active_tasks()
+            .filter_map(|t| t.completed_at)
+            .filter(|at| at.date_naive() >= since)
+            .count()
+    }
+
+    /// Completions per day over the `days` days ending today, including days
+    /// with none.
+    pub fn completions_per_day(&self, today: NaiveDate, days: usize) -> BTreeMap<NaiveDate, usize> {
+        let mut counts: BTreeMap<NaiveDate, usize> = (0..days as i64)
+            .map(|offset| (today - Duration::days(offset), 0))
+            .collect();
+        for at in self.active_tasks().filter_map(|t| t.completed_at) {
+            if let Some(count) = counts.get_mut(&at.date_naive()) {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
+    /// How many tasks outside the trash carry each tag.
+    pub fn tag_counts(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for tag in self.active_tasks().flat_map(|t| &t.tags) {
+            *counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Writes the list in its manual order, with each task's tags sorted, so
+    /// saving an unchanged list always produces the same bytes.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut sorted = self.clone();
+        for task in &mut sorted.tasks {
+            task.normalize_tags();
+            task.tags.sort();
+        }
+        let contents = serde_json::to_string_pretty(&sorted)?;
+        fs::write(path, contents).with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    /// Reads a task file, upgrading tasks saved by older versions. A missing
+    /// file gives an empty list.
+    pub fn load(path: &Path) -> Result<Self> {
+        if path.exists() {
+            let contents = read_text_file(path)?;
+            let mut manager: TaskManager = serde_json::from_str(&contents)?;
+            let legacy: LegacyFile = serde_json::from_str(&contents)?;
+            for (task, old) in manager.tasks.iter_mut().zip(legacy.tasks) {
+                if old.status.is_none() && old.completed == Some(true) {
+                    task.status = Status::Done;
+                }
+            }
+            for task in manager.tasks.iter_mut() {
+                task.normalize_tags();
+                if task.uuid.is_empty() {
+                    task.uuid = new_uuid();
+                }
+            }
+            manager.tag_meta = std::mem::take(&mut manager.tag_meta)
+                .into_iter()
+                .filter_map(|(tag, meta)| Some((normalize_tag(&tag)?, meta)))
+                .collect();
+            Ok(manager)
+        } else {
+            Ok(TaskManager::new())
+        }
+    }
+
+    /// Moves the done and cancelled tasks into the archive file at `path`,
+    /// which is created if needed, and returns how many moved. The archive is
+    /// written before anything leaves this list.
+    pub fn archive_finished(&mut self, path: &Path) -> Result<usize> {
+        let finished = |t: &Task| t.trashed_at.is_none() && !t.status.is_open();
+
+        let mut archive = TaskManager::load(path)?;
+        let moved: Vec<Task> = self.tasks.iter().filter(|t| finished(t)).cloned().collect();
+        let count = moved.len();
+        archive.tasks.extend(moved);
+        archive.next_id = archive.tasks.iter().map(|t| t.id + 1).fold(archive.next_id, usize::max);
+        archive.save(path)?;
+
+        self.tasks.retain(|t| !finished(t));
+        Ok(count)
+    }
+
+    /// Merges the tasks of `other` into this list. Tasks whose uuid is already
+    /// known replace the local copy if they were updated more recently; the
+    /// rest are appended under fresh ids, optionally skipping exact duplicates.
+    pub fn merge(&mut self, other: TaskManager, skip_duplicates: bool) -> Result<MergeReport> {
+        let mut report = MergeReport::default();
+        for mut task in other.tasks {
+            if let Some(local) = self.tasks.iter_mut().find(|t| t.uuid == task.uuid) {
+                if local.same_content(&task) || local.updated_at >= task.updated_at {
+                    report.skipped += 1;
+                } else {
+                    task.id = local.id;
+                    *local = task;
+                    report.updated += 1;
+                }
+                continue;
+            }
+            if skip_duplicates && self.tasks.iter().any(|t| t.same_content(&task)) {
+                report.skipped += 1;
+                continue;
+            }
+            task.id = self.take_id()?;
+            self.add_task(task);
+            report.added += 1;
+        }
+        // Tag metadata already here stays as it is.
+        for (tag, meta) in other.tag_meta {
+            self.tag_meta.entry(tag).or_insert(meta);
+        }
+        Ok(report)
+    }
+
+    /// Problems in a hand-edited file that `load` alone would not catch.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut seen = HashSet::new();
+        for task in &self.tasks {
+            if !seen.insert(task.id) {
+                problems.push(format!("Duplicate task id #{}", task.id));
+            }
+            if task.description.contains(char::is_control) {
+                problems.push(format!(
+                    "Task #{} has a line break or tab in its description; use notes for long text",
+                    task.id
+                ));
+            }
+        }
+        if let Some(max_id) = self.tasks.iter().map(|t| t.id).max()
+            && self.next_id <= max_id
+        {
+            problems.push(format!(
+                "next_id is {} but task #{} already exists",
+                self.next_id, max_id
+            ));
+        }
+        problems
+    }
+
+    /// Prints the listing for `view` to stdout.
+    pub fn list_tasks(&self, view: &View, config: &Config) {
+        print!("{}", self.render_tasks(view, config, today()));
+    }
+
+    /// Writes the listing to `path` exactly as `list_tasks` shows it, minus colors.
+    pub fn write_tasks(&self, path: &Path, view: &View, config: &Config) -> Result<()> {
+        colored::control::set_override(false);
+        let listing = self.render_tasks(view, config, today());
+        colored::control::unset_override();
+
+        fs::write(path, listing)
+            .with_context(|| format!("Could not write listing to {}", path.display()))
+    }
+
+    /// The tasks `view` shows, in its order. The filter may carry date
+    /// predicates such as `due:overdue`, judged as of `today`; see
+    /// `parse_date_predicates`.
+    pub fn view_tasks(&self, view: &View, config: &Config, today: NaiveDate) -> Vec<&Task> {
+        let (dates, text) = parse_date_predicates(view.filter.as_deref().unwrap_or(""), today);
+        let query = (!text.is_empty()).then(|| text.to_lowercase());
+        // Aliases are resolved once per distinct tag rather than once per task.
+        let tag_names: HashSet<&str> = match (&view.tag, &query) {
+            (None, None) => HashSet::new(),
+            _ => self.active_tasks().flat_map(|t| &t.tags).map(String::as_str).collect(),
+        };
+        let spellings = |tag: &str| -> HashSet<&str> {
+            let Some(tag) = config.canonical_tag(tag) else {
+                return HashSet::new();
+            };
+            let same = |name: &&str| config.canonical_tag(name).as_ref() == Some(&tag);
+            tag_names.iter().copied().filter(same).collect()
+        };
+        let has_tag = |task: &Task, spellings: &HashSet<&str>| {
+            task.tags.iter().any(|own| spellings.contains(own.as_str()))
+        };
+        let view_tag = view.tag.as_deref().map(spellings);
+        let query_tag = query.as_deref().map(spellings).unwrap_or_default();
+        let mut tasks: Vec<&Task> = self
+            .active_tasks()
+            .filter(|task| task.someday == view.someday)
+            .filter(|task| dates(task))
+            .filter(|task| !view.hide_completed || task.status.is_open())
+            .filter(|task| view_tag.as_ref().is_none_or(|tag| has_tag(task, tag)))
+            .filter(|task| view.min_priority.is_none_or(|min| task.priority >= min))
+            .filter(|task| !view.waiting || (task.status.is_open() && task.waiting_on.is_some()))
+            .filter(|task| match &query {
+                Some(query) => {
+                    has_tag(task, &query_tag)
+                        || contains_ignore_case(&task.description, query)
+                }
+                None => true,
+            })
+            .collect();
+
+        match view.sort {
+            SortOrder::Id => tasks.sort_by_key(|t| t.id),
+            SortOrder::DueDate => tasks.sort_by(|a, b| cmp_tasks(a, b)),
+            SortOrder::Updated => {
+                tasks.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then_with(|| cmp_tasks(a, b)))
+            }
+            SortOrder::Manual => {}
+        }
+        tasks
+    }
+
+    /// The listing for `view` as `list_tasks` prints it.
+    pub fn render_tasks(&self, view: &View, config: &Config, today: NaiveDate) -> String {
+        let mut out = String::new();
+
+        if !config.quiet {
+            writeln!(out, "\n{}", tr("Tasks:").bold().underline()).unwrap();
+        }
+        let tasks = self.view_tasks(view, config, today);
+        if tasks.is_empty() && !config.quiet {
+            let message = if self.active_tasks().next().is_none() {
+                tr("No tasks yet — add one!").to_string()
+            } else if let Some(filter) = &view.filter {
+                format!("{} '{}'", tr("No tasks matched"), filter)
+            } else {
+                tr("No tasks to show in this view.").to_string()
+            };
+            writeln!(out, "{}", message.dimmed()).unwrap();
+        }
+        let (_, highlight) = parse_date_predicates(view.filter.as_deref().unwrap_or(""), today);
+        let highlight = (!highlight.is_empty()).then_some(highlight.as_str());
+        let columns = terminal_width();
+        let palette = Palette::detect();
+        for task in &tasks {
+            let line = render_task_in(task, config, today, highlight, columns, &palette);
+            writeln!(out, "{}", line).unwrap();
+        }
+        let filtered = view.filter.is_some() || view.tag.is_some();
+        if !tasks.is_empty() && filtered && !config.quiet {
+            let summary = format!("{} {}", tasks.len(), tr("matching task(s)"));
+            writeln!(out, "{}", summary.dimmed()).unwrap();
+        }
+        if !config.quiet {
+            writeln!(out).unwrap();
+        }
+        out
+    }
+}
+
+/// The listing line for a single task, with any case-insensitive occurrence
+/// of `highlight` in the description emphasized.
+pub fn render_task(
+    task: &Task,
+    config: &Config,
+    today: NaiveDate,
+    highlight: Option<&str>,
+) -> String {
+    render_task_in(task, config, today, highlight, terminal_width(), &Palette::detect())
+}
+
+/// `render_task` for a terminal `columns` wide in `palette`, so a whole
+/// listing asks for the width and colors only once.
+fn render_task_in(
+    task: &Task,
+    config: &Config,
+    today: NaiveDate,
+    highlight: Option<&str>,
+    columns: usize,
+    palette: &Palette,
+) -> String {
+    let row_color = if !config.highlight_overdue_rows {
+        None
+    } else if task.is_overdue(today) {
+        Some(palette.overdue)
+    } else if task.is_due_today(today) {
+        Some(palette.due_today)
+    } else {
+        None
+    };
+    let status = match task.status {
+        Status::Done => "[✓]".color(palette.done),
+        Status::Cancelled => "[✗]".color(palette.cancelled),
+        _ if task.is_overdue(today) => "[!]".color(palette.overdue),
+        _ if task.is_due_today(today) => "[!]".color(palette.due_today),
+        Status::InProgress => "[~]".color(palette.in_progress),
+        Status::Todo => "[ ]".normal(),
+    };
+
+    let mut parts = vec![
+        status,
+        format!("#{}", task.id).color(palette.id),
+    ];
+    match task.priority {
+        Priority::High => parts.push("↑".color(palette.high)),
+        Priority::Low => parts.push("↓".color(palette.low)),
+        Priority::Medium => {}
+    }
+    // A colored row is flattened to plain text, which would lose the highlight.
+    let ranges = match highlight {
+        Some(query) if row_color.is_none() => match_ranges(&task.description, &query.to_lowercase()),
+        _ => Vec::new(),
+    };
+
+    // A colored row is flattened to plain text, so it wins over the task's color.
+    let custom_color = match &task.color {
+        Some(name) if row_color.is_none() => name.parse::<Color>().ok(),
+        _ => None,
+    };
+
+    // Wrapped lines continue under the first character of the description.
+    let indent: usize = parts.iter().map(|p| measure_text_width(&p.input) + 1).sum();
+    let width = columns.saturating_sub(indent);
+    let wrap = config.wrap_descriptions && width >= MIN_WRAP_WIDTH;
+    let lines = if wrap {
+        wrap_lines(&task.description, width)
+    } else {
+        vec![(0, task.description.len())]
+    };
+    let styled: Vec<String> = lines
+        .iter()
+        .map(|&(from, to)| {
+            let text = &task.description[from..to];
+            let ranges: Vec<(usize, usize)> = ranges
+                .iter()
+                .filter(|&&(start, end)| start < to && end > from)
+                .map(|&(start, end)| (start.max(from) - from, end.min(to) - from))
+                .collect();
+            let styled = match task.status {
+                Status::Cancelled => text.strikethrough(),
+                _ if !ranges.is_empty() => return highlight_ranges(text, &ranges),
+                _ => text.normal(),
+            };
+            match custom_color {
+                Some(color) => styled.color(color).to_string(),
+                None => styled.to_string(),
+            }
+        })
+        .collect();
+    let line_break = format!("\n{}", " ".repeat(indent));
+    parts.push(styled.join(&line_break).normal());
+
+    let mut tail = Vec::new();
+    if let Some(name) = task.waiting_on.as_ref().filter(|_| task.status.is_open()) {
+        tail.push(format!("⏳ {}: {}", tr("waiting"), name).color(palette.in_progress));
+    }
+    if let Some(due_date) = task.due_date {
+        let due_str = format!("({})", due_date.format("%Y-%m-%d"));
+        let due_display = if due_date < today {
+            due_str.color(palette.overdue)
+        } else if due_date == today {
+            due_str.color(palette.due_today)
+        } else {
+            due_str.normal()
+        };
+        tail.push(due_display);
+    } else if task.status.is_open() && !config.hide_no_date_marker {
+        tail.push(tr("(no date)").dimmed());
+    }
+
+    if !task.tags.is_empty() && !config.hide_tags {
+        // A colored row is flattened to plain text, which would keep the codes.
+        tail.push(if row_color.is_some() {
+            config.tag_style.render(&task.tags).normal()
+        } else {
+            let paint = |tag: &str| tag.color(tag_color(tag, config)).to_string();
+            config.tag_style.render_with(&task.tags, paint).normal()
+        });
+    }
+
+    let join = |parts: &[ColoredString]| match row_color {
+        Some(color) => {
+            let plain: Vec<&str> = parts.iter().map(|p| p.input.as_str()).collect();
+            plain.join(" ").color(color).to_string()
+        }
+        None => {
+            let line: Vec<String> = parts.iter().map(|p| p.to_string()).collect();
+            line.join(" ")
+        }
+    };
+    if tail.is_empty() {
+        return join(&parts);
+    }
+    // The due date and tags move to a line of their own when they don't fit
+    // after the last line of a wrapped description.
+    let tail_width: usize = tail.iter().map(|p| measure_text_width(&p.input) + 1).sum();
+    let last_width = lines.last().map_or(0, |&(from, to)| {
+        measure_text_width(&task.description[from..to])
+    });
+    let gap = if wrap && last_width + tail_width > width {
+        line_break.as_str()
+    } else {
+        " "
+    };
+    format!("{}{}{}", join(&parts), gap, join(&tail))
+}
@@ -0,0 +1,88 @@
+use chrono::NaiveDate;
+
+use crate::task::{parse_due_date, Task};
+
+/// A condition on a task's due date that a search can carry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateCondition {
+    Before(NaiveDate),
+    After(NaiveDate),
+    On(NaiveDate),
+    Overdue,
+    Today,
+    Undated,
+}
+
+impl DateCondition {
+    /// Whether `task`'s due date meets the condition, as of `today`.
+    pub fn matches(self, task: &Task, today: NaiveDate) -> bool {
+        match self {
+            DateCondition::Before(date) => task.due_date.is_some_and(|due| due < date),
+            DateCondition::After(date) => task.due_date.is_some_and(|due| due > date),
+            DateCondition::On(date) => task.due_date == Some(date),
+            DateCondition::Overdue => task.is_overdue(today),
+            DateCondition::Today => task.due_date == Some(today),
+            DateCondition::Undated => task.due_date.is_none(),
+        }
+    }
+
+    /// Reads the value of a `due:` term: `overdue`, `today`, `none` (or
+    /// `undated`), or a date, which may be relative like `+1w`.
+    fn parse_term(value: &str, today: NaiveDate) -> Option<DateCondition> {
+        match value.to_lowercase().as_str() {
+            "overdue" => Some(DateCondition::Overdue),
+            "today" => Some(DateCondition::Today),
+            "none" | "undated" => Some(DateCondition::Undated),
+            _ => parse_due_date(value, today).map(DateCondition::On),
+        }
+    }
+}
+
+/// Splits the date predicates out of a search: `due:overdue`, `due:today`,
+/// `due:none`, `due:<date>`, and `due before|after|on <date>`. Returns a
+/// filter that holds for the tasks meeting all of them, and the rest of the
+/// search, which stays a tag or text query. Terms that don't parse as
+/// predicates are left in the text.
+pub fn parse_date_predicates(
+    query: &str,
+    today: NaiveDate,
+) -> (impl Fn(&Task) -> bool + use<>, String) {
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let mut conditions = Vec::new();
+    let mut text = Vec::new();
+    let mut index = 0;
+    while index < words.len() {
+        let word = words[index];
+        let phrase = match words.get(index..index + 3) {
+            Some(&[due, relation, date]) if due.eq_ignore_ascii_case("due") => {
+                parse_due_date(date, today).and_then(|date| {
+                    match relation.to_lowercase().as_str() {
+                        "before" => Some(DateCondition::Before(date)),
+                        "after" => Some(DateCondition::After(date)),
+                        "on" => Some(DateCondition::On(date)),
+                        _ => None,
+                    }
+                })
+            }
+            _ => None,
+        };
+        if let Some(condition) = phrase {
+            conditions.push(condition);
+            index += 3;
+            continue;
+        }
+
+        let term = word
+            .get(..4)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("due:"))
+            .and_then(|_| DateCondition::parse_term(&word[4..], today));
+        match term {
+            Some(condition) => conditions.push(condition),
+            None => text.push(word),
+        }
+        index += 1;
+    }
+
+    let filter = move |task: &Task| conditions.iter().all(|c| c.matches(task, today));
+    (filter, text.join(" "))
+}
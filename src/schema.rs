@@ -0,0 +1,101 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::task::{Priority, Recurrence, Status};
+
+/// The serialized names of `values`, for an `enum` keyword.
+fn names<T: Serialize>(values: &[T]) -> Value {
+    values.iter().map(|v| serde_json::to_value(v).unwrap_or(Value::Null)).collect()
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "anyOf": [schema, { "type": "null" }] })
+}
+
+/// JSON Schema (draft 2020-12) for the task file that `TaskManager::save`
+/// writes. The field list mirrors `TaskManager` and `Task` and needs updating
+/// alongside them; the enum values come from the types themselves.
+pub fn task_file_schema() -> Value {
+    let date = json!({ "type": "string", "format": "date" });
+    let timestamp = json!({ "type": "string", "format": "date-time" });
+    let counter = json!({ "type": "integer", "minimum": 0 });
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "PlanSync task file",
+        "type": "object",
+        "required": ["tasks", "next_id"],
+        "properties": {
+            "tasks": { "type": "array", "items": { "$ref": "#/$defs/task" } },
+            "next_id": {
+                "description": "The id the next new task gets; ids are never reused.",
+                "type": "integer",
+                "minimum": 1
+            },
+            "tag_meta": {
+                "description": "Colors, aliases and descriptions of tags, by tag.",
+                "type": "object",
+                "additionalProperties": { "$ref": "#/$defs/tag_meta" }
+            }
+        },
+        "$defs": {
+            "tag_meta": {
+                "type": "object",
+                "properties": {
+                    "color": nullable(json!({ "type": "string" })),
+                    "aliases": { "type": "array", "items": { "type": "string" } },
+                    "description": nullable(json!({ "type": "string" }))
+                }
+            },
+            "task": {
+                "type": "object",
+                "required": ["id", "description", "tags", "due_date"],
+                "properties": {
+                    "id": counter,
+                    "description": { "type": "string" },
+                    "status": { "enum": names(&Status::ALL), "default": "todo" },
+                    "completed": {
+                        "description": "Written by versions before status; read as done or todo.",
+                        "type": "boolean",
+                        "deprecated": true
+                    },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "due_date": nullable(date),
+                    "notes": { "type": "string", "default": "" },
+                    "priority": { "enum": names(&Priority::ALL), "default": "medium" },
+                    "someday": { "type": "boolean", "default": false },
+                    "recurrence": nullable(json!({ "enum": names(&Recurrence::ALL) })),
+                    "recurrence_day": nullable(json!({
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 31
+                    })),
+                    "waiting_on": nullable(json!({ "type": "string" })),
+                    "estimate_minutes": nullable(counter),
+                    "time_entries": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["started_at", "ended_at"],
+                            "properties": { "started_at": timestamp, "ended_at": timestamp }
+                        }
+                    },
+                    "comments": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["text", "created_at"],
+                            "properties": { "text": { "type": "string" }, "created_at": timestamp }
+                        }
+                    },
+                    "attachments": { "type": "array", "items": { "type": "string" } },
+                    "color": nullable(json!({ "type": "string" })),
+                    "uuid": { "type": "string" },
+                    "updated_at": timestamp,
+                    "completed_at": nullable(timestamp.clone()),
+                    "trashed_at": nullable(timestamp)
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+use plansync::i18n::tr;
+use plansync::manager::TaskManager;
+use plansync::task::Task;
+
+/// How long a connection may sit idle before it is dropped.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Answers `GET /tasks` on `port` with the tasks outside the trash, as JSON,
+/// until the process is stopped. The task file is read afresh for every
+/// request and never written, so the menu can keep editing it meanwhile.
+/// Only connections from this machine are accepted. Requests are answered
+/// one at a time, so a client that goes quiet is dropped after `TIMEOUT`.
+pub fn serve(data_file: &Path, port: u16, quiet: bool) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .with_context(|| format!("Could not listen on port {}", port))?;
+    if !quiet {
+        let url = format!("http://127.0.0.1:{}/tasks", port);
+        println!("{} {} ({})", tr("Serving tasks on"), url, tr("Ctrl-C to stop"));
+    }
+    for stream in listener.incoming() {
+        let result = stream.map_err(Into::into).and_then(|s| respond(s, data_file));
+        if let Err(e) = result {
+            eprintln!("Request failed: {:#}", e);
+        }
+    }
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream, data_file: &Path) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are read but not needed.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = target.split('?').next().unwrap_or("");
+    let (status, body) = match (method, path) {
+        ("GET", "/tasks") => match TaskManager::load(data_file) {
+            Ok(manager) => {
+                let tasks: Vec<&Task> = manager.active_tasks().collect();
+                ("200 OK", serde_json::to_string_pretty(&tasks)?)
+            }
+            Err(e) => ("500 Internal Server Error", error_body(&format!("{:#}", e))),
+        },
+        (_, "/tasks") => ("405 Method Not Allowed", error_body("Only GET is supported")),
+        _ => ("404 Not Found", error_body("Not found")),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
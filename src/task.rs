@@ -0,0 +1,423 @@
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// How important a task is. Ordered so that `High` compares greatest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl Priority {
+    /// Every priority, highest first.
+    pub const ALL: [Priority; 3] = [Priority::High, Priority::Medium, Priority::Low];
+
+    /// Reads a priority as given on the command line.
+    pub fn parse(name: &str) -> Option<Priority> {
+        Priority::ALL.into_iter().find(|p| p.label().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// The priority's English name, as shown and parsed.
+    pub fn label(self) -> &'static str {
+        match self {
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+        }
+    }
+}
+
+/// Where a task stands. Files from before this existed only recorded a
+/// `completed` flag, which `TaskManager::load` maps to `Done` or `Todo`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    #[default]
+    Todo,
+    InProgress,
+    Done,
+    Cancelled,
+}
+
+impl Status {
+    /// Every status, in workflow order.
+    pub const ALL: [Status; 4] = [Status::Todo, Status::InProgress, Status::Done, Status::Cancelled];
+
+    /// The status's English name, as shown in listings.
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Todo => "To do",
+            Status::InProgress => "In progress",
+            Status::Done => "Done",
+            Status::Cancelled => "Cancelled",
+        }
+    }
+
+    /// Whether the task still needs doing.
+    pub fn is_open(self) -> bool {
+        matches!(self, Status::Todo | Status::InProgress)
+    }
+}
+
+/// How often a task comes back once it's done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Recurrence {
+    /// Every recurrence, shortest interval first.
+    pub const ALL: [Recurrence; 3] = [Recurrence::Daily, Recurrence::Weekly, Recurrence::Monthly];
+
+    /// The recurrence's English name, as shown in listings.
+    pub fn label(self) -> &'static str {
+        match self {
+            Recurrence::Daily => "Daily",
+            Recurrence::Weekly => "Weekly",
+            Recurrence::Monthly => "Monthly",
+        }
+    }
+
+    /// The due date of the occurrence after one due on `due`. Monthly tasks
+    /// fall on `day` of the next month, or on its last day when the month is
+    /// shorter, so a task on the 31st lands on Feb 28 and then Mar 31.
+    pub fn next_due(self, due: NaiveDate, day: u32) -> NaiveDate {
+        let next = match self {
+            Recurrence::Daily => due.checked_add_days(Days::new(1)),
+            Recurrence::Weekly => due.checked_add_days(Days::new(7)),
+            Recurrence::Monthly => day_of_next_month(due, day),
+        };
+        next.unwrap_or(due)
+    }
+
+    /// The due dates of the `count` occurrences following one due on `due`.
+    pub fn upcoming(self, due: NaiveDate, count: usize) -> Vec<NaiveDate> {
+        std::iter::successors(Some(due), |&date| Some(self.next_due(date, due.day())))
+            .skip(1)
+            .take(count)
+            .collect()
+    }
+}
+
+/// Whether `date` falls on a Saturday or Sunday.
+pub fn is_weekend(date: NaiveDate) -> bool {
+    matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// `date` itself on a weekday, or else the Monday after it.
+pub fn next_business_day(date: NaiveDate) -> NaiveDate {
+    match date.weekday() {
+        Weekday::Sat => date + Days::new(2),
+        Weekday::Sun => date + Days::new(1),
+        _ => date,
+    }
+}
+
+/// `day` of the month after the one holding `date`, clamped to that month's
+/// last day.
+fn day_of_next_month(date: NaiveDate, day: u32) -> Option<NaiveDate> {
+    let first = date.with_day(1)?.checked_add_months(Months::new(1))?;
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?.day();
+    first.with_day(day.clamp(1, last))
+}
+
+/// A stretch of time spent working on a task.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeEntry {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+}
+
+/// A timestamped remark in a task's running log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A single to-do item. Fields added after the first release default when
+/// missing, so older task files still load.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: usize,
+    pub description: String,
+    #[serde(default)]
+    pub status: Status,
+    pub tags: Vec<String>,
+    pub due_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub notes: String,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Parked in the someday/maybe list, out of the regular views.
+    #[serde(default)]
+    pub someday: bool,
+    /// Completing a repeating task adds its next occurrence.
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    /// Day of the month a monthly task is due on, kept across occurrences
+    /// that a short month moves earlier. The due date's day when unset.
+    #[serde(default)]
+    pub recurrence_day: Option<u32>,
+    /// Who the task is waiting on, for delegated or blocked work.
+    #[serde(default)]
+    pub waiting_on: Option<String>,
+    /// How long the task should take, in minutes.
+    #[serde(default)]
+    pub estimate_minutes: Option<u32>,
+    #[serde(default)]
+    pub time_entries: Vec<TimeEntry>,
+    /// Oldest first.
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    /// File paths or URLs the task refers to.
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    /// Name of a color for the description in listings, e.g. "bright blue".
+    /// Names `colored` doesn't know are ignored.
+    #[serde(default)]
+    pub color: Option<String>,
+    /// Identifies the same task across files; `id` is only a local handle.
+    #[serde(default)]
+    pub uuid: String,
+    /// When the task last changed. Tasks from before this field existed
+    /// default to the Unix epoch, so any recorded change wins a merge.
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
+    /// When the task was completed, for tasks completed since this was tracked.
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Set while the task sits in the trash, hidden from every view.
+    #[serde(default)]
+    pub trashed_at: Option<DateTime<Utc>>,
+}
+
+impl Task {
+    /// Records that the task changed just now.
+    pub fn touch(&mut self) {
+        self.updated_at = Utc::now();
+    }
+
+    /// Open and due before `today`.
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        self.status.is_open() && self.due_date.is_some_and(|due| due < today)
+    }
+
+    /// Open and due on `today`.
+    pub fn is_due_today(&self, today: NaiveDate) -> bool {
+        self.status.is_open() && self.due_date == Some(today)
+    }
+
+    /// Total time logged against the task, in whole minutes.
+    pub fn tracked_minutes(&self) -> i64 {
+        self.time_entries
+            .iter()
+            .map(|entry| (entry.ended_at - entry.started_at).num_minutes())
+            .sum()
+    }
+
+    /// Whether the description reads the same as `description`, ignoring case
+    /// and spacing.
+    pub fn same_description(&self, description: &str) -> bool {
+        single_line(&self.description).to_lowercase() == single_line(description).to_lowercase()
+    }
+
+    /// Cleans up tags written by hand: each is normalized, and repeats are
+    /// dropped, keeping the first.
+    pub fn normalize_tags(&mut self) {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.tags.iter().filter_map(|tag| normalize_tag(tag)) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        self.tags = tags;
+    }
+
+    /// Whether both tasks hold the same data, ignoring their ids.
+    pub fn same_content(&self, other: &Task) -> bool {
+        self.description == other.description
+            && self.status == other.status
+            && self.tags == other.tags
+            && self.due_date == other.due_date
+            && self.notes == other.notes
+            && self.priority == other.priority
+            && self.someday == other.someday
+            && self.recurrence == other.recurrence
+            && self.estimate_minutes == other.estimate_minutes
+            && self.waiting_on == other.waiting_on
+            && self.comments == other.comments
+            && self.attachments == other.attachments
+            && self.color == other.color
+    }
+}
+
+/// The default order of tasks in listings: open tasks before done ones, and
+/// done before cancelled; then by due date, earliest first and undated last;
+/// then by priority, highest first; then by id. Ids are unique, so no two
+/// tasks compare equal and the order never depends on where a task sits.
+pub fn cmp_tasks(a: &Task, b: &Task) -> Ordering {
+    let rank = |task: &Task| match task.status {
+        Status::Todo | Status::InProgress => 0,
+        Status::Done => 1,
+        Status::Cancelled => 2,
+    };
+    rank(a)
+        .cmp(&rank(b))
+        .then_with(|| a.due_date.is_none().cmp(&b.due_date.is_none()))
+        .then_with(|| a.due_date.cmp(&b.due_date))
+        .then_with(|| b.priority.cmp(&a.priority))
+        .then_with(|| a.id.cmp(&b.id))
+}
+
+/// Fits a description on one listing line: line breaks, tabs and runs of
+/// spaces become single spaces, other control characters are dropped, and
+/// the ends are trimmed.
+pub fn single_line(text: &str) -> String {
+    let text: String = text.chars().filter(|c| c.is_whitespace() || !c.is_control()).collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// What a task file records about one of its tags, so the file carries it
+/// when shared. The config's `tag_aliases` and `tag_colors` take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TagMeta {
+    /// A color name, as in `tag_colors`.
+    pub color: Option<String>,
+    /// Other spellings that are stored as this tag.
+    pub aliases: Vec<String>,
+    /// What the tag is for, shown in Task Details.
+    pub description: Option<String>,
+}
+
+/// Cleans up a typed tag: drops a leading `#` or `@`, lowercases it and joins
+/// words with hyphens, so " #Home Office " becomes "home-office". Returns
+/// `None` when nothing is left.
+pub fn normalize_tag(raw: &str) -> Option<String> {
+    let tag = raw.trim().trim_start_matches(['#', '@']);
+    let tag = tag.split_whitespace().collect::<Vec<_>>().join("-");
+    if tag.is_empty() {
+        None
+    } else {
+        Some(tag.to_lowercase())
+    }
+}
+
+/// Reads a due date typed as `YYYY-MM-DD` or as an offset from `base` such as
+/// `+1d`, `-2d`, `+1w` or `+1m`.
+pub fn parse_due_date(input: &str, base: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Some(date);
+    }
+
+    let (forward, rest) = match input.as_bytes().first()? {
+        b'+' => (true, &input[1..]),
+        b'-' => (false, &input[1..]),
+        _ => return None,
+    };
+    let unit = rest.chars().last()?;
+    let amount: u32 = rest[..rest.len() - unit.len_utf8()].parse().ok()?;
+    let days = match unit.to_ascii_lowercase() {
+        'd' => amount as u64,
+        'w' => amount as u64 * 7,
+        'm' => {
+            let months = Months::new(amount);
+            return if forward {
+                base.checked_add_months(months)
+            } else {
+                base.checked_sub_months(months)
+            };
+        }
+        _ => return None,
+    };
+    if forward {
+        base.checked_add_days(Days::new(days))
+    } else {
+        base.checked_sub_days(Days::new(days))
+    }
+}
+
+/// A task typed on one line, as read by `parse_quick_add`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickAdd {
+    pub description: String,
+    /// As typed, without the `#`; not yet normalized.
+    pub tags: Vec<String>,
+    pub due_date: Option<NaiveDate>,
+    pub priority: Option<Priority>,
+}
+
+/// Reads the priority of a `!` token: `!high`, `!medium` or `!low`, `!1` to
+/// `!3` with 1 the highest, or `!!!`, `!!` and `!` likewise.
+fn parse_priority_token(token: &str) -> Option<Priority> {
+    let value = token.strip_prefix('!')?;
+    match value.to_lowercase().as_str() {
+        "high" | "1" | "!!" => Some(Priority::High),
+        "medium" | "2" | "!" => Some(Priority::Medium),
+        "low" | "3" | "" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// Splits a one-line task such as `Call Bob #work due:+1d !high` into its
+/// description and the `#tag`, `due:<date>` and `!priority` tokens found
+/// anywhere in it. Tokens that don't parse stay in the description; of
+/// repeated due dates or priorities, the last wins.
+pub fn parse_quick_add(input: &str, today: NaiveDate) -> QuickAdd {
+    let mut quick = QuickAdd {
+        description: String::new(),
+        tags: Vec::new(),
+        due_date: None,
+        priority: None,
+    };
+    let mut words = Vec::new();
+    for word in input.split_whitespace() {
+        let due = word
+            .get(..4)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("due:"))
+            .and_then(|_| parse_due_date(&word[4..], today));
+        if let Some(tag) = word.strip_prefix('#').filter(|tag| !tag.is_empty()) {
+            quick.tags.push(tag.to_string());
+        } else if let Some(due) = due {
+            quick.due_date = Some(due);
+        } else if let Some(priority) = parse_priority_token(word) {
+            quick.priority = Some(priority);
+        } else {
+            words.push(word);
+        }
+    }
+    quick.description = words.join(" ");
+    quick
+}
+
+/// Today's date in UTC.
+pub fn today() -> NaiveDate {
+    Utc::now().naive_utc().date()
+}
+
+/// A random (version 4) UUID in its hyphenated form.
+pub fn new_uuid() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("no system random number source");
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}